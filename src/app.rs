@@ -1,12 +1,24 @@
+mod action_log;
+pub mod history;
 pub mod journal;
 pub mod level;
+pub mod period;
+pub mod prompt;
+pub mod record;
 mod selectable_list;
-mod tab;
+pub mod tab;
+mod tabs;
 
-use std::{sync::Arc, time::Duration};
+use std::{
+  path::{Path, PathBuf},
+  sync::{self, Arc},
+  time::Duration,
+};
 
-use anyhow::{Context, Result};
-use futures::{Future, FutureExt};
+use anyhow::Result;
+use chrono::{Local, NaiveDate};
+use futures::Future;
+use ratatui::style::Color;
 use tokio::{
   sync::{watch, Mutex},
   task::{AbortHandle, JoinHandle},
@@ -14,12 +26,34 @@ use tokio::{
 };
 
 use self::{
-  journal::Journal, selectable_list::SelectableList, tab::Tab,
+  action_log::{ActionLog, Change},
+  history::Action,
+  period::Period,
+  prompt::{Edit, Prompt, PromptKind},
+  record::{parse_date, parse_time, Record},
+  selectable_list::SelectableList,
+  tab::{Applied, Tab},
 };
+use crate::{cfg::Config, keymap::Keymap, theme::Styles};
 
 #[derive(Default, Clone, PartialEq)]
 pub struct State {
-  pub tabs: SelectableList<String>,
+  pub tabs: SelectableList<TabTitle>,
+  pub journals_dir: PathBuf,
+  pub prompt: Option<Prompt>,
+  /// Scroll offset of the history panel when it's shown
+  pub history_scroll: Option<usize>,
+  /// Cursor date of the heatmap view when it's shown
+  pub heatmap: Option<NaiveDate>,
+  /// Whether undo and redo follow the action log of all tabs
+  pub global_undo: bool,
+  /// Transient message about the last undo or redo
+  pub status: Option<String>,
+  /// Failure of the last background task until it's dismissed
+  pub error: Option<String>,
+  /// Bound keys of the active keymap, most needed first
+  pub help: Vec<String>,
+  styles: Styles,
   inner: tab::State,
 }
 
@@ -31,100 +65,156 @@ impl std::ops::Deref for State {
   }
 }
 
+#[derive(Default, Clone, PartialEq)]
+pub struct TabTitle {
+  /// Identifies the tab while tabs are added and reordered
+  pub id: usize,
+  pub text: String,
+  pub color: Option<Color>,
+}
+
 impl State {
-  pub fn new(tabs: impl IntoIterator<Item = String>) -> Self {
+  /// Styles of the theme with the accent of the active tab
+  pub fn styles(&self) -> Styles {
+    let color = self.tabs.selected_item().and_then(|tab| tab.color);
+    self.styles.with_accent(color)
+  }
+
+  pub fn new(journals_dir: &Path) -> Self {
     Self {
-      tabs: tabs.into_iter().collect(),
+      journals_dir: journals_dir.to_owned(),
       ..Default::default()
     }
   }
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq, Eq)]
 pub enum Command {
   NextTab,
+  PrevTab,
+  SelectTab(usize),
+  SwitchTab,
   PrevDate,
   NextDate,
+  SelectDate(NaiveDate),
+  GoToDate,
+  Today,
+  PrevFrame,
+  NextFrame,
+  TogglePeriod,
   PrevSelection,
   NextSelection,
+  SelectRecord(usize),
   AddRecord,
+  AddRecordAt,
   DeleteSelectedRecord,
+  EditSelectedRecord,
+  MoveSelectedRecord,
+  EditPrompt(Edit),
+  ShiftPromptTime(i64),
+  SubmitPrompt,
+  CancelPrompt,
+  ToggleHistory,
+  ScrollHistoryUp,
+  ScrollHistoryDown,
+  ToggleHeatmap,
+  MoveHeatmapCursor(i64),
+  OpenHeatmapDate,
+  ToggleGlobalUndo,
+  NewTab,
+  RenameTab,
+  MoveTabLeft,
+  MoveTabRight,
+  ArchiveTab,
+  RetryFailed,
+  DismissError,
   Undo,
   Redo,
   Quit,
 }
 
+/// Re-runs the failed task
+type Retry = Box<dyn FnOnce(&mut App) + Send>;
+
 pub struct App {
+  cfg: Config,
+  keymap: Keymap,
   tabs: SelectableList<Arc<Mutex<Tab>>>,
-  state_tx: Arc<Mutex<watch::Sender<State>>>,
+  next_tab_id: usize,
+  /// Days shown in the days chart of every tab
+  days: u64,
+  state_tx: Arc<watch::Sender<State>>,
   state_rx: watch::Receiver<State>,
+  log: Arc<sync::Mutex<ActionLog>>,
+  retry: Arc<sync::Mutex<Option<Retry>>>,
   abort_handle: AbortHandle,
   should_quit: bool,
 }
 
 impl App {
-  pub fn new<I, S>(args: I) -> Self
-  where
-    I: IntoIterator<Item = (S, usize, Box<dyn Journal>)>,
-    S: Into<String>,
-  {
-    let tabs: Vec<_> = args
-      .into_iter()
-      .map(|(t, c, j)| Tab::new(t, c, j))
-      .collect();
-    let tab_titles = tabs.iter().map(|tab| tab.title().clone());
-    let (state_tx, state_rx) = watch::channel(State::new(tab_titles));
-    let state_tx = Arc::new(Mutex::new(state_tx));
-
-    Self::subscribe_on_tabs(
-      &tabs,
-      state_rx.clone(),
-      state_tx.clone(),
-    );
-
-    let tabs =
-      tabs.into_iter().map(|t| Arc::new(Mutex::new(t))).collect();
+  pub fn new(cfg: Config) -> Result<Self> {
+    let mut warnings = vec![];
+    let tabs = cfg
+      .tabs
+      .iter()
+      .map(|tab| {
+        let (tab, warning) = tabs::open(&cfg, tab)?;
+        warnings.extend(warning);
+        Ok(tab)
+      })
+      .collect::<Result<Vec<_>>>()?;
+    let keymap = Keymap::new(&cfg.keys)?;
+    let (state_tx, state_rx) = watch::channel(State {
+      help: keymap.help(),
+      styles: Styles::new(&cfg.theme),
+      ..State::new(&cfg.journals_dir)
+    });
     let abort_handle =
       tokio::spawn(async { Ok(()) as Result<()> }).abort_handle();
 
-    Self {
-      tabs,
-      state_tx,
+    let mut app = Self {
+      cfg,
+      keymap,
+      tabs: SelectableList::new(),
+      next_tab_id: 0,
+      days: tab::DEFAULT_DAYS,
+      state_tx: Arc::new(state_tx),
       state_rx,
+      log: Arc::default(),
+      retry: Arc::default(),
       abort_handle,
       should_quit: false,
+    };
+    for tab in tabs {
+      app.push_tab(tab)?;
+    }
+    if !warnings.is_empty() {
+      tokio::spawn(show_status(
+        app.state_tx.clone(),
+        warnings.join("; "),
+      ));
     }
+    Ok(app)
   }
 
-  fn subscribe_on_tabs(
-    tabs: &[Tab],
-    mut state_rx: watch::Receiver<State>,
-    state_tx: Arc<Mutex<watch::Sender<State>>>,
-  ) {
+  /// Forwards state of the tab to the app state while it's active
+  fn subscribe_on_tab(&self, id: usize, tab: &Tab) {
     const FPS: u64 = 60;
     const DEBOUNCE: Duration = Duration::from_millis(1000 / FPS);
 
-    let mut active_tab = 0;
-    let mut tab_states: Vec<_> =
-      tabs.iter().map(Tab::subscribe).collect();
+    let mut tab_state = tab.subscribe();
+    let state_rx = self.state_rx.clone();
+    let state_tx = self.state_tx.clone();
 
     tokio::spawn(async move {
-      loop {
+      while tab_state.changed().await.is_ok() {
         sleep(DEBOUNCE).await;
-        let tab_state = &mut tab_states[active_tab];
-        let tab_switched = state_rx
-          .wait_for(|state| state.tabs.selected() != active_tab)
-          .map(|_| ());
-        tokio::select! {
-          _ = tab_state.changed() => {
-            let tab_state = tab_state.borrow().clone();
-            let state_tx = state_tx.lock().await;
-            state_tx.send_modify(|state| state.inner = tab_state);
-          }
-          () = tab_switched => {
-            active_tab = state_rx.borrow().tabs.selected();
-          }
+        // Marked as seen first, the tab is resolved again when shown
+        let tab_state = tab_state.borrow_and_update().clone();
+        if active_tab_id(&state_rx.borrow()) != Some(id) {
+          continue;
         }
+        state_tx.send_modify(|state| state.inner = tab_state);
       }
     });
   }
@@ -136,65 +226,408 @@ impl App {
     self
   }
 
-  pub fn state(&self) -> watch::Ref<State> {
+  /// Re-resolves the tab when its journal changed externally while
+  /// it's active. Stops when the tab is dropped
+  fn watch_journal(&self, id: usize, tab: &Arc<Mutex<Tab>>) {
+    const DEBOUNCE: Duration = Duration::from_millis(100);
+
+    let tab = Arc::downgrade(tab);
+    let state_rx = self.state_rx.clone();
+    let state_tx = self.state_tx.clone();
+    let retry = self.retry.clone();
+    tokio::spawn(async move {
+      let changes = match tab.upgrade() {
+        Some(tab) => tab.lock().await.watch_journal(),
+        None => return,
+      };
+      let mut changes = match changes {
+        Ok(Some(changes)) => changes,
+        Ok(None) => return,
+        Err(err) => {
+          let watch = Box::new(move |app: &mut App| {
+            if let Some(tab) = app.tab_by_id(id) {
+              app.watch_journal(id, &tab);
+            }
+          });
+          let err = err.context("can't watch journal for changes");
+          report_failure(&state_tx, &retry, &err, watch).await;
+          return;
+        }
+      };
+      while changes.changed().await.is_ok() {
+        sleep(DEBOUNCE).await;
+        changes.borrow_and_update();
+        if active_tab_id(&state_rx.borrow()) != Some(id) {
+          continue;
+        }
+        let Some(tab) = tab.upgrade() else {
+          return;
+        };
+        let resolved = tab.lock().await.resolve_all().await;
+        if let Err(err) = resolved {
+          let resolve =
+            Box::new(move |app: &mut App| app.resolve(id));
+          report_failure(&state_tx, &retry, &err, resolve).await;
+        }
+      }
+    });
+  }
+
+  pub fn keymap(&self) -> &Keymap {
+    &self.keymap
+  }
+
+  pub fn state(&self) -> watch::Ref<'_, State> {
     self.state_rx.borrow()
   }
 
   pub fn handle_cmd(&mut self, cmd: Command) -> Result<()> {
     use Command::{
-      AddRecord, DeleteSelectedRecord, NextDate, NextSelection,
-      NextTab, PrevDate, PrevSelection, Quit, Redo, Undo,
+      AddRecord, AddRecordAt, ArchiveTab, CancelPrompt,
+      DeleteSelectedRecord, DismissError, EditPrompt,
+      EditSelectedRecord, GoToDate, MoveHeatmapCursor,
+      MoveSelectedRecord, MoveTabLeft, MoveTabRight, NewTab,
+      NextDate, NextFrame, NextSelection, NextTab, OpenHeatmapDate,
+      PrevDate, PrevFrame, PrevSelection, PrevTab, Quit, Redo,
+      RenameTab, RetryFailed, ScrollHistoryDown, ScrollHistoryUp,
+      SelectDate, SelectRecord, SelectTab, ShiftPromptTime,
+      SubmitPrompt, SwitchTab, Today, ToggleGlobalUndo,
+      ToggleHeatmap, ToggleHistory, TogglePeriod, Undo,
     };
 
     match cmd {
       NextTab => self.next_tab()?,
+      PrevTab => self.prev_tab()?,
+      SelectTab(i) if i < self.tabs.len() => self.select_tab(i)?,
+      SelectTab(_) => {}
+      SwitchTab => self
+        .set_prompt(Some(Prompt::new(PromptKind::SwitchTab, "")))?,
       PrevDate => self.spawn_tab_abortable(|tab| async move {
         tab.lock().await.prev_date().await
       }),
       NextDate => self.spawn_tab_abortable(|tab| async move {
         tab.lock().await.next_date().await
       }),
+      GoToDate => {
+        let date = self.state().date.format("%F").to_string();
+        self.set_prompt(Some(Prompt::new(
+          PromptKind::GoToDate,
+          date,
+        )))?;
+      }
+      Today => self.jump_to(Local::now().date_naive()),
+      PrevFrame => self.spawn_tab_abortable(|tab| async move {
+        tab.lock().await.prev_frame().await
+      }),
+      NextFrame => self.spawn_tab_abortable(|tab| async move {
+        tab.lock().await.next_frame().await
+      }),
+      TogglePeriod => self.spawn_tab_abortable(|tab| async move {
+        tab.lock().await.next_period().await
+      }),
+      SelectDate(date) => {
+        self.spawn_tab_abortable(move |tab| async move {
+          tab.lock().await.select_date(date).await
+        });
+      }
       PrevSelection => self.spawn_tab_abortable(|tab| async move {
         tab.lock().await.prev_selection()
       }),
       NextSelection => self.spawn_tab_abortable(|tab| async move {
         tab.lock().await.next_selection()
       }),
-      AddRecord => self.spawn_tab_blocking(|tab| async move {
+      SelectRecord(i) => {
+        self.spawn_tab_abortable(move |tab| async move {
+          tab.lock().await.select_record(i)
+        })
+      }
+      AddRecord => self.spawn_tab_action(|tab| async move {
         tab.lock().await.add_record().await
       }),
+      AddRecordAt => {
+        let now = Local::now().format("%R").to_string();
+        self.set_prompt(Some(Prompt::new(
+          PromptKind::AddRecordAt,
+          now,
+        )))?;
+      }
+      DeleteSelectedRecord | EditSelectedRecord
+      | MoveSelectedRecord
+        if self.state().period != Period::Day =>
+      {
+        tokio::spawn(show_status(
+          self.state_tx.clone(),
+          "switch to day view to change records".into(),
+        ));
+      }
       DeleteSelectedRecord => {
-        self.spawn_tab_blocking(|tab| async move {
+        self.spawn_tab_action(|tab| async move {
           tab.lock().await.delete_selected_record().await
         });
       }
-      Undo => self.spawn_tab_blocking(|tab| async move {
-        tab.lock().await.undo().await
-      }),
-      Redo => self.spawn_tab_blocking(|tab| async move {
-        tab.lock().await.redo().await
-      }),
+      EditSelectedRecord => self.edit_selected_record()?,
+      MoveSelectedRecord => self.move_selected_record()?,
+      EditPrompt(edit) => self.modify_prompt(|p| p.edit(edit))?,
+      ShiftPromptTime(minutes) => {
+        self.modify_prompt(|p| p.shift_time(minutes))?;
+      }
+      SubmitPrompt => self.submit_prompt()?,
+      CancelPrompt => self.set_prompt(None)?,
+      ToggleHistory => self.modify_state(|state| {
+        state.history_scroll = state.history_scroll.xor(Some(0));
+      })?,
+      ScrollHistoryUp => self.modify_state(|state| {
+        state.history_scroll =
+          state.history_scroll.map(|s| s.saturating_sub(1));
+      })?,
+      ScrollHistoryDown => self.modify_state(|state| {
+        let last = state.history.len().saturating_sub(1);
+        state.history_scroll =
+          state.history_scroll.map(|s| last.min(s + 1));
+      })?,
+      ToggleHeatmap => self.toggle_heatmap()?,
+      MoveHeatmapCursor(days) => self.modify_state(|state| {
+        let today = Local::now().date_naive();
+        let start = tab::heatmap_start(today);
+        let days = chrono::Duration::days(days);
+        state.heatmap =
+          state.heatmap.map(|date| (date + days).clamp(start, today));
+      })?,
+      OpenHeatmapDate => {
+        let date = self.state().heatmap;
+        self.modify_state(|state| state.heatmap = None)?;
+        if let Some(date) = date {
+          self.jump_to(date);
+        }
+      }
+      ToggleGlobalUndo => {
+        self.modify_state(|state| state.global_undo ^= true)?;
+        let status = if self.state().global_undo {
+          "undo follows all tabs"
+        } else {
+          "undo follows the active tab"
+        };
+        tokio::spawn(show_status(
+          self.state_tx.clone(),
+          status.into(),
+        ));
+      }
+      NewTab => self.new_tab()?,
+      RenameTab => self.rename_tab()?,
+      MoveTabLeft => self.move_tab(-1)?,
+      MoveTabRight => self.move_tab(1)?,
+      ArchiveTab => self.archive_tab()?,
+      RetryFailed => {
+        self.modify_state(|state| state.error = None)?;
+        let retry = self.retry.lock().expect("poisoned retry").take();
+        if let Some(retry) = retry {
+          retry(self);
+        }
+      }
+      DismissError => {
+        self.modify_state(|state| state.error = None)?;
+        self.retry.lock().expect("poisoned retry").take();
+      }
+      Undo => self.undo(),
+      Redo => self.redo(),
       Quit => self.should_quit = true,
     }
     Ok(())
   }
 
   fn next_tab(&mut self) -> Result<()> {
+    let next = (self.tabs.selected() + 1) % self.tabs.len();
+    self.select_tab(next)
+  }
+
+  fn prev_tab(&mut self) -> Result<()> {
+    let len = self.tabs.len();
+    let prev = (self.tabs.selected() + len - 1) % len;
+    self.select_tab(prev)
+  }
+
+  /// Demounts the active tab and resolves the tab `i` instead
+  fn select_tab(&mut self, i: usize) -> Result<()> {
     self.spawn_tab_blocking(|tab| async move {
       tab.lock().await.demount()
     });
-    self.tabs.wrapping_select_next();
-    self
-      .state_tx
-      .try_lock()
-      .context("use lock?!")?
-      .send_modify(|state| state.tabs.select(self.tabs.selected()));
+    self.show_tab(i)
+  }
+
+  fn show_tab(&mut self, i: usize) -> Result<()> {
+    self.tabs.select(i);
+    self.modify_state(|state| state.tabs.select(i))?;
     self.spawn_tab_abortable(|tab| async move {
       tab.lock().await.resolve_all().await
     });
     Ok(())
   }
 
+  /// Fits days chart of the tabs to `fit` days, or fewer if
+  /// configured
+  pub fn resize_days(&mut self, fit: u64) {
+    let days = self.cfg.days.map_or(fit, |days| days.min(fit)).max(2);
+    if days == self.days {
+      return;
+    }
+    self.days = days;
+    let active = self.tabs.selected();
+    for (i, tab) in self.tabs.iter().enumerate() {
+      if i != active {
+        let tab = tab.clone();
+        tokio::spawn(
+          async move { tab.lock().await.resize_days(days) },
+        );
+      }
+    }
+    self.spawn_tab_abortable(move |tab| async move {
+      tab.lock().await.set_days(days).await
+    });
+  }
+
+  fn toggle_heatmap(&mut self) -> Result<()> {
+    if self.state().heatmap.is_some() {
+      return self.modify_state(|state| state.heatmap = None);
+    }
+    let today = Local::now().date_naive();
+    let start = tab::heatmap_start(today);
+    self.modify_state(|state| {
+      state.heatmap = Some(state.date.clamp(start, today));
+    })?;
+    self.spawn_tab_abortable(|tab| async move {
+      tab.lock().await.resolve_heatmap().await
+    });
+    Ok(())
+  }
+
+  fn jump_to(&mut self, date: NaiveDate) {
+    self.spawn_tab_abortable(move |tab| async move {
+      tab.lock().await.jump_to(date).await
+    });
+  }
+
+  fn edit_selected_record(&mut self) -> Result<()> {
+    let details = match self.state().list.selected_item() {
+      Some(rec) => rec.details(),
+      None => return Ok(()),
+    };
+    self
+      .set_prompt(Some(Prompt::new(PromptKind::EditRecord, details)))
+  }
+
+  fn move_selected_record(&mut self) -> Result<()> {
+    let time = match self.state().list.selected_item() {
+      Some(rec) => rec.time.format("%R").to_string(),
+      None => return Ok(()),
+    };
+    self.set_prompt(Some(Prompt::new(PromptKind::MoveRecord, time)))
+  }
+
+  fn submit_prompt(&mut self) -> Result<()> {
+    let Some(prompt) = self.state().prompt.clone() else {
+      return Ok(());
+    };
+    let date = self.state().date;
+    match prompt.kind.clone() {
+      PromptKind::AddRecordAt | PromptKind::MoveRecord => {
+        let time = match parse_time(date, &prompt.input) {
+          Ok(time) => time,
+          Err(err) => {
+            return self
+              .modify_prompt(|p| p.error = Some(format!("{err:#}")));
+          }
+        };
+        if prompt.kind == PromptKind::AddRecordAt {
+          self.spawn_tab_action(move |tab| async move {
+            tab.lock().await.add_record_at(time).await
+          });
+        } else {
+          self.spawn_tab_action(move |tab| async move {
+            tab.lock().await.move_selected_record(time).await
+          });
+        }
+      }
+      PromptKind::GoToDate => {
+        let today = Local::now().date_naive();
+        match parse_date(today, &prompt.input) {
+          Ok(date) => self.jump_to(date),
+          Err(err) => {
+            return self
+              .modify_prompt(|p| p.error = Some(format!("{err:#}")));
+          }
+        }
+      }
+      PromptKind::EditRecord => {
+        let input = prompt.input;
+        if let Err(err) =
+          Record::new(Local::now()).with_details(&input)
+        {
+          return self
+            .modify_prompt(|p| p.error = Some(format!("{err:#}")));
+        }
+        self.spawn_tab_action(move |tab| {
+          let input = input.clone();
+          async move {
+            tab.lock().await.edit_selected_record(&input).await
+          }
+        });
+      }
+      _ => return self.submit_tab_prompt(prompt),
+    }
+    self.set_prompt(None)
+  }
+
+  fn undo(&mut self) {
+    let tab = self.history_tab(ActionLog::last_undo_tab);
+    self.spawn_logged(tab, Change::Undone, |tab| async move {
+      tab.lock().await.undo().await
+    });
+  }
+
+  fn redo(&mut self) {
+    let tab = self.history_tab(ActionLog::last_redo_tab);
+    self.spawn_logged(tab, Change::Redone, |tab| async move {
+      tab.lock().await.redo().await
+    });
+  }
+
+  /// Id of the tab to undo or redo in, `None` when the global log
+  /// is empty
+  fn history_tab(
+    &self,
+    last: fn(&ActionLog) -> Option<usize>,
+  ) -> Option<usize> {
+    if self.state().global_undo {
+      last(&self.log.lock().expect("poisoned log"))
+    } else {
+      active_tab_id(&self.state())
+    }
+  }
+
+  fn tab_by_id(&self, id: usize) -> Option<Arc<Mutex<Tab>>> {
+    let i = self.state().tabs.iter().position(|tab| tab.id == id)?;
+    self.tabs.get(i).cloned()
+  }
+
+  fn set_prompt(&mut self, prompt: Option<Prompt>) -> Result<()> {
+    self.modify_state(|state| state.prompt = prompt)
+  }
+
+  fn modify_prompt(
+    &mut self,
+    f: impl FnOnce(&mut Prompt),
+  ) -> Result<()> {
+    self.modify_state(|state| state.prompt.as_mut().map_or((), f))
+  }
+
+  fn modify_state(
+    &mut self,
+    f: impl FnOnce(&mut State),
+  ) -> Result<()> {
+    self.state_tx.send_modify(f);
+    Ok(())
+  }
+
   fn spawn_tab_abortable<F>(
     &mut self,
     f: impl Fn(Arc<Mutex<Tab>>) -> F + Send + 'static,
@@ -205,6 +638,8 @@ impl App {
     self.abort_handle = self.spawn_tab(f).abort_handle();
   }
 
+  /// Spawns a task on the active tab, which is resolved again to
+  /// retry on failure
   fn spawn_tab<F>(
     &self,
     f: impl Fn(Arc<Mutex<Tab>>) -> F + Send + 'static,
@@ -212,8 +647,113 @@ impl App {
   where
     F: Future<Output = Result<()>> + Send,
   {
+    let id = active_tab_id(&self.state());
     let tab = self.tabs.selected_item().unwrap().clone();
-    tokio::spawn(async move { f(tab).await.unwrap() })
+    let state_tx = self.state_tx.clone();
+    let retry = self.retry.clone();
+    tokio::spawn(async move {
+      if let Err(err) = f(tab).await {
+        let resolve = Box::new(move |app: &mut App| {
+          app.resolve(id.unwrap_or_default());
+        });
+        report_failure(&state_tx, &retry, &err, resolve).await;
+      }
+    })
+  }
+
+  /// Resolves the tab again if it's still active
+  fn resolve(&mut self, id: usize) {
+    if active_tab_id(&self.state()) == Some(id) {
+      self.spawn_tab_abortable(|tab| async move {
+        tab.lock().await.resolve_all().await
+      });
+    }
+  }
+
+  /// Spawns an action on the active tab, keeping it in the log
+  fn spawn_tab_action<F>(
+    &self,
+    f: impl Fn(Arc<Mutex<Tab>>) -> F + Clone + Send + 'static,
+  ) where
+    F: Future<Output = Result<Option<Action>>> + Send,
+  {
+    let id = active_tab_id(&self.state());
+    self.spawn_logged(id, Change::Performed, f);
+  }
+
+  /// Spawns an action on the tab with id `tab`, retried as is on
+  /// failure unless it's already in the journal
+  fn spawn_logged<F>(
+    &self,
+    tab: Option<usize>,
+    change: Change,
+    f: impl Fn(Arc<Mutex<Tab>>) -> F + Clone + Send + 'static,
+  ) where
+    F: Future<Output = Result<Option<Action>>> + Send,
+  {
+    self.abort_handle.abort();
+    let tab = tab.and_then(|id| Some((id, self.tab_by_id(id)?)));
+    let log = self.log.clone();
+    let state_tx = self.state_tx.clone();
+    let retry = self.retry.clone();
+    tokio::spawn(async move {
+      let (action, title) = match tab {
+        Some((i, tab)) => {
+          let title = tab.lock().await.title().clone();
+          let action = match f.clone()(tab).await {
+            Ok(action) => action,
+            Err(err) => {
+              let again: Retry = match err.downcast_ref() {
+                Some(Applied(action)) => {
+                  let mut log = log.lock().expect("poisoned log");
+                  log.record(i, change, action.clone());
+                  Box::new(move |app: &mut App| app.finish(i))
+                }
+                None => Box::new(move |app: &mut App| {
+                  app.spawn_logged(Some(i), change, f);
+                }),
+              };
+              report_failure(&state_tx, &retry, &err, again).await;
+              return;
+            }
+          };
+          if let Some(action) = &action {
+            let mut log = log.lock().expect("poisoned log");
+            log.record(i, change, action.clone());
+          }
+          (action, title)
+        }
+        None => (None, String::new()),
+      };
+      let status = match (change, action) {
+        (Change::Performed, _) => return,
+        (Change::Undone, None) => "nothing to undo".into(),
+        (Change::Redone, None) => "nothing to redo".into(),
+        (Change::Undone, Some(action)) => {
+          format!("{title}: undone {}", action.description())
+        }
+        (Change::Redone, Some(action)) => {
+          format!("{title}: redone {}", action.description())
+        }
+      };
+      show_status(state_tx, status).await;
+    });
+  }
+
+  /// Saves history of the tab with id `id` and resolves it again,
+  /// retried on failure
+  fn finish(&self, id: usize) {
+    let Some(tab) = self.tab_by_id(id) else {
+      return;
+    };
+    let state_tx = self.state_tx.clone();
+    let retry = self.retry.clone();
+    tokio::spawn(async move {
+      if let Err(err) = tab.lock().await.finish().await {
+        let again = Box::new(move |app: &mut App| app.finish(id));
+        report_failure(&state_tx, &retry, &err, again).await;
+      }
+    });
   }
 
   fn spawn_tab_blocking<F>(
@@ -234,3 +774,102 @@ impl App {
     self.should_quit
   }
 }
+
+/// Shows `status` for a few seconds unless it's replaced
+async fn show_status(
+  state_tx: Arc<watch::Sender<State>>,
+  status: String,
+) {
+  const TIMEOUT: Duration = Duration::from_secs(3);
+
+  state_tx.send_modify(|state| state.status = Some(status.clone()));
+  sleep(TIMEOUT).await;
+  state_tx.send_if_modified(|state| {
+    let shown = state.status.as_ref() == Some(&status);
+    if shown {
+      state.status = None;
+    }
+    shown
+  });
+}
+
+/// Shows the error until it's dismissed or `retry` is requested
+async fn report_failure(
+  state_tx: &watch::Sender<State>,
+  retry_slot: &sync::Mutex<Option<Retry>>,
+  err: &anyhow::Error,
+  retry: Retry,
+) {
+  *retry_slot.lock().expect("poisoned retry") = Some(retry);
+  let message = format!("{err:#}");
+  state_tx.send_modify(|state| state.error = Some(message));
+}
+
+fn active_tab_id(state: &State) -> Option<usize> {
+  state.tabs.selected_item().map(|tab| tab.id)
+}
+
+#[cfg(test)]
+mod tests {
+  use chrono::TimeZone;
+  use tokio::time::timeout;
+
+  use super::{history::History, journal::Journal, *};
+  use crate::fs_journal::FsJournal;
+
+  #[tokio::test(flavor = "multi_thread")]
+  async fn should_resolve_active_tab_on_external_changes() {
+    let dir = tempfile::tempdir().unwrap();
+    let cfg = Config::default().with_journals_dir(dir.path().into());
+    let noon = Local.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap();
+    let date = noon.date_naive();
+    let mut app = App::new(cfg).unwrap();
+    let mut state = app.state_rx.clone();
+    // Watches before the write for it to be noticed
+    let tab = app.tabs.selected_item().unwrap().clone();
+    tab.lock().await.watch_journal().unwrap();
+    app.jump_to(date);
+    wait_for(&mut state, |state| state.date == date).await;
+    assert!(app.state().list.is_empty());
+
+    let other = FsJournal::new(dir.path().join("smokes"));
+    other.add(Record::new(noon)).await.unwrap();
+    wait_for(&mut state, |state| !state.list.is_empty()).await;
+  }
+
+  #[tokio::test(flavor = "multi_thread")]
+  async fn should_not_apply_action_again_on_retry() {
+    let dir = tempfile::tempdir().unwrap();
+    let cfg = Config::default().with_journals_dir(dir.path().into());
+    let tab_dir = dir.path().join("smokes");
+    let history = tab_dir.join("history.toml");
+    // Blocks saving of history
+    let tmp = tab_dir.join("history.toml.tmp");
+    std::fs::create_dir_all(&tmp).unwrap();
+    let mut app = App::new(cfg).unwrap().init();
+    let mut state = app.state_rx.clone();
+
+    app.handle_cmd(Command::AddRecord).unwrap();
+    wait_for(&mut state, |state| state.error.is_some()).await;
+    std::fs::remove_dir(&tmp).unwrap();
+    app.handle_cmd(Command::RetryFailed).unwrap();
+    wait_for(&mut state, |_| history.is_file()).await;
+
+    let journal = FsJournal::new(&tab_dir);
+    let mut count = 0;
+    for date in journal.dates().await.unwrap() {
+      count += journal.day_records(date).await.unwrap().len();
+    }
+    assert_eq!(1, count);
+    let history = History::load(&history).unwrap();
+    assert_eq!(1, history.entries().count());
+  }
+
+  async fn wait_for(
+    state: &mut watch::Receiver<State>,
+    f: impl FnMut(&State) -> bool,
+  ) {
+    let waited = timeout(Duration::from_secs(5), state.wait_for(f));
+    waited.await.expect("state in time").unwrap();
+  }
+}
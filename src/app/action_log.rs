@@ -0,0 +1,87 @@
+use super::history::Action;
+
+#[derive(Clone, Copy)]
+pub enum Change {
+  Performed,
+  Undone,
+  Redone,
+}
+
+/// Actions of the session in order across all tabs, keyed by tab
+/// id
+#[derive(Default)]
+pub struct ActionLog {
+  undoes: Vec<(usize, Action)>,
+  redoes: Vec<(usize, Action)>,
+}
+
+impl ActionLog {
+  pub fn record(
+    &mut self,
+    tab: usize,
+    change: Change,
+    action: Action,
+  ) {
+    match change {
+      Change::Performed => {
+        self.undoes.push((tab, action));
+        self.redoes.clear();
+      }
+      Change::Undone => {
+        Self::remove_last(&mut self.undoes, tab);
+        self.redoes.push((tab, action));
+      }
+      Change::Redone => {
+        Self::remove_last(&mut self.redoes, tab);
+        self.undoes.push((tab, action));
+      }
+    }
+  }
+
+  /// Tab of the last performed action
+  pub fn last_undo_tab(&self) -> Option<usize> {
+    self.undoes.last().map(|(tab, _)| *tab)
+  }
+
+  /// Tab of the last undone action
+  pub fn last_redo_tab(&self) -> Option<usize> {
+    self.redoes.last().map(|(tab, _)| *tab)
+  }
+
+  /// Drops actions of the removed tab
+  pub fn forget(&mut self, tab: usize) {
+    self.undoes.retain(|(t, _)| *t != tab);
+    self.redoes.retain(|(t, _)| *t != tab);
+  }
+
+  fn remove_last(actions: &mut Vec<(usize, Action)>, tab: usize) {
+    if let Some(i) = actions.iter().rposition(|(t, _)| *t == tab) {
+      actions.remove(i);
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::app::record::Record;
+
+  use super::*;
+
+  #[test]
+  fn should_track_tabs_of_undone_actions() {
+    let rec: Record = "2024-01-01T12:00:00+00:00".parse().unwrap();
+    let add = || Action::Add(rec.clone());
+    let mut log = ActionLog::default();
+    log.record(0, Change::Performed, add());
+    log.record(1, Change::Performed, add());
+    log.record(0, Change::Performed, add());
+    // Undone in tab 1 directly, out of global order
+    log.record(1, Change::Undone, add());
+    assert_eq!(Some(0), log.last_undo_tab());
+    assert_eq!(Some(1), log.last_redo_tab());
+    log.record(0, Change::Undone, add());
+    log.record(0, Change::Undone, add());
+    assert_eq!(None, log.last_undo_tab());
+    assert_eq!(Some(0), log.last_redo_tab());
+  }
+}
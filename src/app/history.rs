@@ -0,0 +1,195 @@
+use std::{
+  fs,
+  ops::Not,
+  path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+
+use super::record::Record;
+
+/// Max count of actions kept for undo
+const LIMIT: usize = 100;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Action {
+  Add(Record),
+  Delete(Record),
+  Edit(Record, Record),
+  Move(Record, Record),
+}
+
+impl Not for Action {
+  type Output = Self;
+
+  fn not(self) -> Self::Output {
+    match self {
+      Action::Add(rec) => Action::Delete(rec),
+      Action::Delete(rec) => Action::Add(rec),
+      Action::Edit(from, to) => Action::Edit(to, from),
+      Action::Move(from, to) => Action::Move(to, from),
+    }
+  }
+}
+
+impl Action {
+  pub fn description(&self) -> String {
+    let time =
+      |rec: &Record| rec.time.format("%-d %b %R").to_string();
+    match self {
+      Action::Add(rec) => format!("added {}", time(rec)),
+      Action::Delete(rec) => format!("deleted {}", time(rec)),
+      Action::Edit(_, to) => {
+        format!("edited {} to `{}`", time(to), to.details())
+      }
+      Action::Move(from, to) => {
+        format!("moved {} to {}", time(from), to.time.format("%R"))
+      }
+    }
+  }
+}
+
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+pub struct Entry {
+  pub at: DateTime<Local>,
+  pub action: Action,
+}
+
+/// Performed and undone actions, saved to a file when it's given
+#[derive(Default, Serialize, Deserialize)]
+pub struct History {
+  #[serde(skip)]
+  path: Option<PathBuf>,
+  #[serde(default)]
+  undoes: Vec<Entry>,
+  #[serde(default)]
+  redoes: Vec<Entry>,
+}
+
+impl History {
+  /// Loads history from `path`, empty one when the file is missing
+  pub fn load(path: &Path) -> Result<Self> {
+    let history = match fs::read_to_string(path) {
+      Ok(s) => toml::from_str(&s)
+        .with_context(|| format!("invalid {}", path.display()))?,
+      Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+        Self::default()
+      }
+      Err(err) => {
+        return Err(err)
+          .with_context(|| format!("can't read {}", path.display()))
+      }
+    };
+    Ok(Self {
+      path: Some(path.to_owned()),
+      ..history
+    })
+  }
+
+  /// Loads history from `path`, falling back to an unsaved empty one
+  /// with the error, so a broken file isn't overwritten
+  pub fn load_or_default(
+    path: &Path,
+  ) -> (Self, Option<anyhow::Error>) {
+    match Self::load(path) {
+      Ok(history) => (history, None),
+      Err(err) => (Self::default(), Some(err)),
+    }
+  }
+
+  pub fn push(&mut self, action: Action) {
+    self.undoes.push(Entry {
+      at: Local::now(),
+      action,
+    });
+    let excess = self.undoes.len().saturating_sub(LIMIT);
+    self.undoes.drain(..excess);
+    self.redoes.clear();
+  }
+
+  /// Last performed action to revert
+  pub fn last_undo(&self) -> Option<&Action> {
+    self.undoes.last().map(|entry| &entry.action)
+  }
+
+  /// Last undone action to perform again
+  pub fn last_redo(&self) -> Option<&Action> {
+    self.redoes.last().map(|entry| &entry.action)
+  }
+
+  /// Marks the last performed action as undone
+  pub fn undo(&mut self) -> Option<&Action> {
+    let entry = self.undoes.pop()?;
+    self.redoes.push(entry);
+    self.redoes.last().map(|entry| &entry.action)
+  }
+
+  /// Marks the last undone action as performed again
+  pub fn redo(&mut self) -> Option<&Action> {
+    let entry = self.redoes.pop()?;
+    self.undoes.push(entry);
+    self.undoes.last().map(|entry| &entry.action)
+  }
+
+  /// Entries from the newest, flagged if undone
+  pub fn entries(&self) -> impl Iterator<Item = (&Entry, bool)> {
+    let redoes = self.redoes.iter().map(|entry| (entry, true));
+    let undoes = self.undoes.iter().rev().map(|entry| (entry, false));
+    redoes.chain(undoes)
+  }
+
+  pub async fn save(&self) -> Result<()> {
+    let Some(path) = &self.path else {
+      return Ok(());
+    };
+    if let Some(dir) = path.parent() {
+      tokio::fs::create_dir_all(dir).await?;
+    }
+    let tmp = path.with_extension("toml.tmp");
+    tokio::fs::write(&tmp, toml::to_string(self)?).await?;
+    tokio::fs::rename(&tmp, path)
+      .await
+      .with_context(|| format!("can't save {}", path.display()))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[tokio::test]
+  async fn should_reload_saved_history() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("history.toml");
+    let rec: Record = "2024-01-01T12:00:00+00:00".parse().unwrap();
+    let edited = rec.clone().with_details("x2 // note").unwrap();
+    let mut history = History::load(&path).unwrap();
+    history.push(Action::Add(rec.clone()));
+    history.push(Action::Edit(rec.clone(), edited.clone()));
+    history.undo();
+    history.save().await.unwrap();
+
+    let mut history = History::load(&path).unwrap();
+    let undone: Vec<_> =
+      history.entries().map(|(_, undone)| undone).collect();
+    assert_eq!(vec![true, false], undone);
+    assert!(history.redo() == Some(&Action::Edit(rec, edited)));
+  }
+
+  #[tokio::test]
+  async fn should_fall_back_on_corrupt_history() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("history.toml");
+    fs::write(&path, "undoes = [oops").unwrap();
+    let (mut history, err) = History::load_or_default(&path);
+    assert!(err.is_some());
+    assert_eq!(0, history.entries().count());
+    let rec: Record = "2024-01-01T12:00:00+00:00".parse().unwrap();
+    history.push(Action::Add(rec));
+    history.save().await.unwrap();
+    assert_eq!("undoes = [oops", fs::read_to_string(&path).unwrap());
+  }
+}
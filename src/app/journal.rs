@@ -1,12 +1,192 @@
+use std::{collections::HashMap, time::SystemTime};
+
 use anyhow::Result;
 use async_trait::async_trait;
 use chrono::{DateTime, Local, NaiveDate};
+use futures::future::try_join_all;
+use tokio::sync::watch;
+
+use super::record::Record;
 
-pub type DayRecords = Vec<DateTime<Local>>;
+pub type DayRecords = Vec<Record>;
+
+/// Version of a stored day, changing with every write to it
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Stamp {
+  /// Modification time of the day file
+  Modified(SystemTime),
+  /// Version of the whole database, shared by all days
+  Version(i64),
+}
 
 #[async_trait]
 pub trait Journal: Send + Sync {
   async fn day_records(&self, date: NaiveDate) -> Result<DayRecords>;
-  async fn add(&self, dt: DateTime<Local>) -> Result<()>;
+  async fn add(&self, rec: Record) -> Result<()>;
   async fn remove(&self, dt: DateTime<Local>) -> Result<()>;
+
+  /// Replaces details of the record with the same time. Records are
+  /// matched by time, so the old one is removed first and restored
+  /// when adding fails
+  async fn update(&self, rec: Record) -> Result<()> {
+    let date = rec.time.date_naive();
+    let old = self
+      .day_records(date)
+      .await?
+      .into_iter()
+      .find(|old| old.time == rec.time);
+    self.remove(rec.time).await?;
+    if let Err(err) = self.add(rec).await {
+      if let Some(old) = old {
+        self.add(old).await?;
+      }
+      return Err(err);
+    }
+    Ok(())
+  }
+
+  /// Moves the record at `from` to `to` at another time, adding it
+  /// before removing so a failure never loses it
+  async fn move_record(
+    &self,
+    from: DateTime<Local>,
+    to: Record,
+  ) -> Result<()> {
+    self.add(to).await?;
+    self.remove(from).await
+  }
+
+  /// Ordered records from `start` to `end` inclusive
+  async fn records_between(
+    &self,
+    start: NaiveDate,
+    end: NaiveDate,
+  ) -> Result<DayRecords> {
+    let recs = days(start, end).map(|date| self.day_records(date));
+    Ok(try_join_all(recs).await?.concat())
+  }
+
+  /// Records amount of every day from `start` to `end` inclusive
+  async fn count_by_day(
+    &self,
+    start: NaiveDate,
+    end: NaiveDate,
+  ) -> Result<Vec<(NaiveDate, usize)>> {
+    let counts = self.records_between(start, end).await?.iter().fold(
+      HashMap::new(),
+      |mut map, rec| {
+        *map.entry(rec.time.date_naive()).or_default() +=
+          rec.amount();
+        map
+      },
+    );
+    Ok(fill_days(start, end, &counts))
+  }
+
+  /// Stamps of stored days from `start` to `end` inclusive, letting
+  /// caches notice external changes. `None` when unsupported
+  async fn stamps_between(
+    &self,
+    _start: NaiveDate,
+    _end: NaiveDate,
+  ) -> Result<Option<HashMap<NaiveDate, Stamp>>> {
+    Ok(None)
+  }
+
+  /// Signals about changes made outside of the journal. `None` when
+  /// unsupported
+  fn watch(&self) -> Result<Option<watch::Receiver<()>>> {
+    Ok(None)
+  }
+
+  /// Records of the day with the count of its stored lines skipped
+  /// as malformed, read at once
+  async fn day_with_malformed(
+    &self,
+    date: NaiveDate,
+  ) -> Result<(DayRecords, usize)> {
+    Ok((self.day_records(date).await?, 0))
+  }
+}
+
+#[async_trait]
+impl<J: Journal + ?Sized> Journal for Box<J> {
+  async fn day_records(&self, date: NaiveDate) -> Result<DayRecords> {
+    (**self).day_records(date).await
+  }
+
+  async fn add(&self, rec: Record) -> Result<()> {
+    (**self).add(rec).await
+  }
+
+  async fn remove(&self, dt: DateTime<Local>) -> Result<()> {
+    (**self).remove(dt).await
+  }
+
+  async fn update(&self, rec: Record) -> Result<()> {
+    (**self).update(rec).await
+  }
+
+  async fn move_record(
+    &self,
+    from: DateTime<Local>,
+    to: Record,
+  ) -> Result<()> {
+    (**self).move_record(from, to).await
+  }
+
+  async fn records_between(
+    &self,
+    start: NaiveDate,
+    end: NaiveDate,
+  ) -> Result<DayRecords> {
+    (**self).records_between(start, end).await
+  }
+
+  async fn count_by_day(
+    &self,
+    start: NaiveDate,
+    end: NaiveDate,
+  ) -> Result<Vec<(NaiveDate, usize)>> {
+    (**self).count_by_day(start, end).await
+  }
+
+  async fn stamps_between(
+    &self,
+    start: NaiveDate,
+    end: NaiveDate,
+  ) -> Result<Option<HashMap<NaiveDate, Stamp>>> {
+    (**self).stamps_between(start, end).await
+  }
+
+  fn watch(&self) -> Result<Option<watch::Receiver<()>>> {
+    (**self).watch()
+  }
+
+  async fn day_with_malformed(
+    &self,
+    date: NaiveDate,
+  ) -> Result<(DayRecords, usize)> {
+    (**self).day_with_malformed(date).await
+  }
+}
+
+pub fn days(
+  start: NaiveDate,
+  end: NaiveDate,
+) -> impl Iterator<Item = NaiveDate> {
+  start.iter_days().take_while(move |date| date <= &end)
+}
+
+/// Counts of every day in range, zero for missing ones
+pub fn fill_days(
+  start: NaiveDate,
+  end: NaiveDate,
+  counts: &HashMap<NaiveDate, usize>,
+) -> Vec<(NaiveDate, usize)> {
+  days(start, end)
+    .map(|date| {
+      (date, counts.get(&date).copied().unwrap_or_default())
+    })
+    .collect()
 }
@@ -0,0 +1,149 @@
+use chrono::{Datelike, Duration, Months, NaiveDate, Weekday};
+
+/// Span of days a tab shows records by
+#[derive(Clone, Copy, Default, PartialEq)]
+pub enum Period {
+  #[default]
+  Day,
+  Week,
+  Month,
+}
+
+impl Period {
+  pub fn next(self) -> Self {
+    match self {
+      Period::Day => Period::Week,
+      Period::Week => Period::Month,
+      Period::Month => Period::Day,
+    }
+  }
+
+  /// First day of the period containing `date`
+  pub fn start(self, date: NaiveDate) -> NaiveDate {
+    match self {
+      Period::Day => date,
+      Period::Week => date
+        .week(Weekday::Mon)
+        .checked_first_day()
+        .unwrap_or(NaiveDate::MIN),
+      Period::Month => date.with_day(1).expect("first day of month"),
+    }
+  }
+
+  /// Last day of the period containing `date`
+  pub fn end(self, date: NaiveDate) -> NaiveDate {
+    self
+      .checked_shift(date, 1)
+      .and_then(|next| next.pred_opt())
+      .unwrap_or(NaiveDate::MAX)
+  }
+
+  /// Start of the period `n` periods after the one containing `date`,
+  /// clamped to the range of dates
+  pub fn shift(self, date: NaiveDate, n: i32) -> NaiveDate {
+    self.checked_shift(date, n).unwrap_or(if n < 0 {
+      NaiveDate::MIN
+    } else {
+      NaiveDate::MAX
+    })
+  }
+
+  fn checked_shift(
+    self,
+    date: NaiveDate,
+    n: i32,
+  ) -> Option<NaiveDate> {
+    let start = self.start(date);
+    match self {
+      Period::Day => {
+        start.checked_add_signed(Duration::days(n.into()))
+      }
+      Period::Week => {
+        start.checked_add_signed(Duration::weeks(n.into()))
+      }
+      Period::Month => add_months(start, n),
+    }
+  }
+
+  /// Sums of `counts` per `n` periods from the one containing
+  /// `first`, keyed by their first days; counts out of them are
+  /// skipped
+  pub fn group(
+    self,
+    first: NaiveDate,
+    n: i32,
+    counts: impl IntoIterator<Item = (NaiveDate, usize)>,
+  ) -> Vec<(NaiveDate, usize)> {
+    let mut buckets: Vec<_> =
+      (0..n).map(|i| (self.shift(first, i), 0)).collect();
+    let Some(&(last, _)) = buckets.last() else {
+      return buckets;
+    };
+    let end = self.end(last);
+    for (date, count) in counts.into_iter().filter(|r| r.0 <= end) {
+      let i = buckets.partition_point(|(start, _)| *start <= date);
+      if let Some(bucket) =
+        i.checked_sub(1).and_then(|i| buckets.get_mut(i))
+      {
+        bucket.1 += count;
+      }
+    }
+    buckets
+  }
+
+  /// Count of days in the period containing `date`
+  pub fn days(self, date: NaiveDate) -> usize {
+    let days = self.end(date) - self.start(date);
+    days.num_days() as usize + 1
+  }
+}
+
+/// `date` moved by `n` months, clamped to the end of the month
+pub fn add_months(date: NaiveDate, n: i32) -> Option<NaiveDate> {
+  let months = Months::new(n.unsigned_abs());
+  if n < 0 {
+    date.checked_sub_months(months)
+  } else {
+    date.checked_add_months(months)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn should_find_period_bounds() {
+    let date = NaiveDate::from_ymd_opt(2024, 2, 14).unwrap();
+    let ymd = |y, m, d| NaiveDate::from_ymd_opt(y, m, d).unwrap();
+    assert_eq!(ymd(2024, 2, 12), Period::Week.start(date));
+    assert_eq!(ymd(2024, 2, 18), Period::Week.end(date));
+    assert_eq!(ymd(2024, 2, 29), Period::Month.end(date));
+    assert_eq!(29, Period::Month.days(date));
+    assert_eq!(ymd(2023, 12, 1), Period::Month.shift(date, -2));
+    assert_eq!(ymd(2024, 2, 5), Period::Week.shift(date, -1));
+    assert_eq!(NaiveDate::MAX, Period::Month.shift(date, i32::MAX));
+    assert_eq!(NaiveDate::MIN, Period::Day.shift(date, i32::MIN));
+  }
+
+  #[test]
+  fn should_group_counts_by_period() {
+    let ymd = |y, m, d| NaiveDate::from_ymd_opt(y, m, d).unwrap();
+    let counts = [
+      (ymd(2024, 1, 31), 9),
+      (ymd(2024, 2, 5), 1),
+      (ymd(2024, 2, 11), 2),
+      (ymd(2024, 2, 12), 3),
+      (ymd(2024, 3, 31), 4),
+      (ymd(2024, 4, 1), 9),
+    ];
+    assert_eq!(
+      vec![(ymd(2024, 2, 5), 3), (ymd(2024, 2, 12), 3)],
+      Period::Week.group(ymd(2024, 2, 7), 2, counts)
+    );
+    assert_eq!(
+      vec![(ymd(2024, 2, 1), 6), (ymd(2024, 3, 1), 4)],
+      Period::Month.group(ymd(2024, 2, 7), 2, counts)
+    );
+  }
+}
@@ -0,0 +1,178 @@
+use chrono::{Duration, NaiveTime};
+
+/// Single line input shown over the journal
+#[derive(Clone, PartialEq)]
+pub struct Prompt {
+  pub kind: PromptKind,
+  pub input: String,
+  /// Cursor position in chars
+  pub cursor: usize,
+  pub error: Option<String>,
+}
+
+#[derive(Clone, PartialEq)]
+pub enum PromptKind {
+  AddRecordAt,
+  EditRecord,
+  MoveRecord,
+  NewTabTitle,
+  NewTabTarget { title: String },
+  NewTabDir { title: String, target: usize },
+  RenameTab,
+  ArchiveTab,
+  SwitchTab,
+  GoToDate,
+}
+
+impl PromptKind {
+  pub fn title(&self) -> &'static str {
+    match self {
+      PromptKind::AddRecordAt => "Add record at",
+      PromptKind::EditRecord => "Edit record",
+      PromptKind::MoveRecord => "Move record to",
+      PromptKind::NewTabTitle => "New tab title",
+      PromptKind::NewTabTarget { .. } => "New tab target",
+      PromptKind::NewTabDir { .. } => "New tab directory",
+      PromptKind::RenameTab => "Rename tab",
+      PromptKind::ArchiveTab => "Archive tab",
+      PromptKind::SwitchTab => "Switch tab",
+      PromptKind::GoToDate => "Go to date",
+    }
+  }
+
+  pub fn hint(&self) -> &'static str {
+    match self {
+      PromptKind::AddRecordAt | PromptKind::MoveRecord => {
+        "HH:MM, UP/DOWN ±1 min, PGUP/PGDN ±5 min"
+      }
+      PromptKind::EditRecord => "x3 #tag // note",
+      PromptKind::NewTabTitle | PromptKind::RenameTab => "title",
+      PromptKind::NewTabTarget { .. } => "records per day",
+      PromptKind::NewTabDir { .. } => {
+        "relative to journals directory"
+      }
+      PromptKind::ArchiveTab => {
+        "y - remove from config, keeping data"
+      }
+      PromptKind::SwitchTab => "part of title",
+      PromptKind::GoToDate => "YYYY-MM-DD or -3d, -2w, -1m, -1y",
+    }
+  }
+
+  /// What ENTER does
+  pub fn submit(&self) -> &'static str {
+    match self {
+      PromptKind::SwitchTab => "switch",
+      PromptKind::GoToDate => "go",
+      _ => "save",
+    }
+  }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Edit {
+  Insert(char),
+  Backspace,
+  Delete,
+  Left,
+  Right,
+  Home,
+  End,
+}
+
+impl Prompt {
+  pub fn new(kind: PromptKind, input: impl Into<String>) -> Self {
+    let input = input.into();
+    Self {
+      kind,
+      cursor: input.chars().count(),
+      input,
+      error: None,
+    }
+  }
+
+  pub fn edit(&mut self, edit: Edit) {
+    let len = self.input.chars().count();
+    match edit {
+      Edit::Insert(c) => {
+        self.input.insert(self.byte_index(), c);
+        self.cursor += 1;
+      }
+      Edit::Backspace if self.cursor > 0 => {
+        self.cursor -= 1;
+        self.input.remove(self.byte_index());
+      }
+      Edit::Delete if self.cursor < len => {
+        self.input.remove(self.byte_index());
+      }
+      Edit::Left => self.cursor = self.cursor.saturating_sub(1),
+      Edit::Right => self.cursor = len.min(self.cursor + 1),
+      Edit::Home => self.cursor = 0,
+      Edit::End => self.cursor = len,
+      Edit::Backspace | Edit::Delete => {}
+    }
+    self.error = None;
+  }
+
+  /// Shifts `HH:MM` input by `minutes`, stopping at the bounds of
+  /// the day as the record stays on its date
+  pub fn shift_time(&mut self, minutes: i64) {
+    let Ok(time) = NaiveTime::parse_from_str(self.input.trim(), "%R")
+    else {
+      return;
+    };
+    let last =
+      NaiveTime::from_hms_opt(23, 59, 0).expect("last minute");
+    let (shifted, days) =
+      time.overflowing_add_signed(Duration::minutes(minutes));
+    let time = match days.signum() {
+      1 => last,
+      -1 => NaiveTime::MIN,
+      _ => shifted,
+    };
+    self.input = time.format("%R").to_string();
+    self.cursor = self.input.chars().count();
+    self.error = None;
+  }
+
+  fn byte_index(&self) -> usize {
+    self
+      .input
+      .char_indices()
+      .nth(self.cursor)
+      .map_or(self.input.len(), |(i, _)| i)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn should_edit_at_cursor() {
+    let mut prompt = Prompt::new(PromptKind::EditRecord, "#légday");
+    prompt.edit(Edit::Home);
+    prompt.edit(Edit::Insert('x'));
+    prompt.edit(Edit::Insert('2'));
+    prompt.edit(Edit::Insert(' '));
+    prompt.edit(Edit::End);
+    prompt.edit(Edit::Backspace);
+    prompt.edit(Edit::Left);
+    prompt.edit(Edit::Left);
+    prompt.edit(Edit::Delete);
+    assert_eq!("x2 #léga", prompt.input);
+    assert_eq!(7, prompt.cursor);
+  }
+
+  #[test]
+  fn should_clamp_shifted_time_to_day() {
+    let mut prompt = Prompt::new(PromptKind::MoveRecord, "23:58");
+    prompt.shift_time(5);
+    assert_eq!("23:59", prompt.input);
+    prompt.shift_time(-1);
+    assert_eq!("23:58", prompt.input);
+    let mut prompt = Prompt::new(PromptKind::MoveRecord, "00:02");
+    prompt.shift_time(-5);
+    assert_eq!("00:00", prompt.input);
+  }
+}
@@ -0,0 +1,283 @@
+use std::{fmt, str::FromStr};
+
+use anyhow::{bail, Context, Result};
+use chrono::{
+  DateTime, Datelike, Duration, Local, NaiveDate, NaiveTime,
+};
+use serde::{
+  de::Error, Deserialize, Deserializer, Serialize, Serializer,
+};
+
+use super::period::add_months;
+
+/// Journal record. Serialized as a line of RFC 3339 time followed by
+/// optional details: `xN` quantity, `#tag`s and `// note`
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Record {
+  pub time: DateTime<Local>,
+  pub quantity: Option<u32>,
+  pub tags: Vec<String>,
+  pub note: Option<String>,
+}
+
+impl Record {
+  pub fn new(time: DateTime<Local>) -> Self {
+    Self {
+      time,
+      quantity: None,
+      tags: vec![],
+      note: None,
+    }
+  }
+
+  /// Count of the record in statistics
+  pub fn amount(&self) -> usize {
+    self.quantity.map_or(1, |q| q as _)
+  }
+
+  /// Details in the line format, empty when there are none
+  pub fn details(&self) -> String {
+    let quantity = self.quantity.map(|q| format!("x{q}"));
+    let tags = self.tags.iter().map(|tag| format!("#{tag}"));
+    let note = self.note.as_ref().map(|note| format!("// {note}"));
+    quantity
+      .into_iter()
+      .chain(tags)
+      .chain(note)
+      .collect::<Vec<_>>()
+      .join(" ")
+  }
+
+  /// Checks that details survive the line format: tags are single
+  /// words and the note is a single line
+  pub fn validate(&self) -> Result<()> {
+    for tag in &self.tags {
+      if tag.is_empty()
+        || tag.contains(char::is_whitespace)
+        || tag.contains("//")
+      {
+        bail!("invalid tag `{tag}`, expected a word without `//`");
+      }
+    }
+    if let Some(note) = &self.note {
+      if note.contains(['\n', '\r']) {
+        bail!("note can't span several lines");
+      }
+    }
+    Ok(())
+  }
+
+  /// Replaces details with parsed ones
+  pub fn with_details(self, details: &str) -> Result<Self> {
+    let (meta, note) = match details.split_once("//") {
+      Some((meta, note)) => (meta, Some(note.trim())),
+      None => (details, None),
+    };
+    let mut rec = Self {
+      note: note.filter(|note| !note.is_empty()).map(Into::into),
+      ..Self::new(self.time)
+    };
+    for token in meta.split_whitespace() {
+      if let Some(tag) = token.strip_prefix('#') {
+        rec.tags.push(tag.into());
+      } else if let Some(quantity) = token.strip_prefix('x') {
+        rec.quantity =
+          Some(quantity.parse().with_context(|| {
+            format!("invalid quantity `{token}`")
+          })?);
+      } else {
+        bail!(
+          "unexpected `{token}`, expected `xN`, `#tag` or `// note`"
+        );
+      }
+    }
+    rec.validate()?;
+    Ok(rec)
+  }
+}
+
+/// Local time of `date` parsed from `HH:MM`
+pub fn parse_time(
+  date: NaiveDate,
+  time: &str,
+) -> Result<DateTime<Local>> {
+  let time = NaiveTime::parse_from_str(time.trim(), "%R")
+    .with_context(|| {
+      format!("invalid time `{time}`, expected `HH:MM`")
+    })?;
+  date
+    .and_time(time)
+    .and_local_timezone(Local)
+    .earliest()
+    .with_context(|| format!("{time} doesn't exist on {date}"))
+}
+
+/// Date parsed from `YYYY-MM-DD` or an offset from `today` like
+/// `-3d`, `-2w`, `-1m` or `+1y`, within years 1 to 9999 so that
+/// views around it stay in range
+pub fn parse_date(
+  today: NaiveDate,
+  input: &str,
+) -> Result<NaiveDate> {
+  let input = input.trim();
+  let date = match NaiveDate::parse_from_str(input, "%F") {
+    Ok(date) => Some(date),
+    Err(_) => parse_offset(today, input)?,
+  };
+  date
+    .filter(|date| (1..=9999).contains(&date.year()))
+    .with_context(|| format!("date `{input}` is out of range"))
+}
+
+/// Date `input` like `-2w` away from `today`, `None` when it's out
+/// of range
+fn parse_offset(
+  today: NaiveDate,
+  input: &str,
+) -> Result<Option<NaiveDate>> {
+  let invalid = || {
+    format!("invalid date `{input}`, expected `YYYY-MM-DD` or `-2w`")
+  };
+  let (sign, offset) = match input.split_at_checked(1) {
+    Some(("-", offset)) => (-1, offset),
+    Some(("+", offset)) => (1, offset),
+    _ => bail!(invalid()),
+  };
+  let Some((i, _)) = offset.char_indices().next_back() else {
+    bail!(invalid());
+  };
+  let (n, unit) = offset.split_at(i);
+  let n = sign * n.parse::<i32>().with_context(invalid)?;
+  Ok(match unit {
+    "d" => today.checked_add_signed(Duration::days(n.into())),
+    "w" => today.checked_add_signed(Duration::weeks(n.into())),
+    "m" => add_months(today, n),
+    "y" => add_months(today, n.saturating_mul(12)),
+    _ => bail!(invalid()),
+  })
+}
+
+/// `time` moved forward past times of `recs`, as records are
+/// identified by time
+pub fn unique_time(
+  recs: &[Record],
+  mut time: DateTime<Local>,
+) -> DateTime<Local> {
+  while recs.iter().any(|rec| rec.time == time) {
+    time += Duration::seconds(1);
+  }
+  time
+}
+
+impl fmt::Display for Record {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let details = self.details();
+    write!(f, "{}", self.time.to_rfc3339())?;
+    if !details.is_empty() {
+      write!(f, " {details}")?;
+    }
+    Ok(())
+  }
+}
+
+impl FromStr for Record {
+  type Err = anyhow::Error;
+
+  fn from_str(line: &str) -> Result<Self> {
+    let line = line.trim();
+    let (time, details) = line.split_once(' ').unwrap_or((line, ""));
+    let time = DateTime::parse_from_rfc3339(time)
+      .context("date parse error")?
+      .with_timezone(&Local);
+    Self::new(time).with_details(details)
+  }
+}
+
+impl Serialize for Record {
+  fn serialize<S: Serializer>(
+    &self,
+    s: S,
+  ) -> Result<S::Ok, S::Error> {
+    s.collect_str(self)
+  }
+}
+
+impl<'de> Deserialize<'de> for Record {
+  fn deserialize<D: Deserializer<'de>>(
+    d: D,
+  ) -> Result<Self, D::Error> {
+    let line = String::deserialize(d)?;
+    line
+      .parse()
+      .map_err(|err| D::Error::custom(format!("{err:#}")))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn should_parse_plain_time_line() {
+    let rec: Record = "2024-01-01T12:00:00+03:00".parse().unwrap();
+    assert_eq!(Record::new(rec.time), rec);
+    assert_eq!(1, rec.amount());
+  }
+
+  #[test]
+  fn should_roundtrip_details() {
+    let line =
+      "2024-01-01T12:00:00+00:00 x3 #leg #day // after coffee";
+    let rec: Record = line.parse().unwrap();
+    assert_eq!(Some(3), rec.quantity);
+    assert_eq!(vec!["leg", "day"], rec.tags);
+    assert_eq!(Some("after coffee"), rec.note.as_deref());
+    assert_eq!(rec, rec.to_string().parse().unwrap());
+  }
+
+  #[test]
+  fn should_reject_details_breaking_line() {
+    let time = "2024-01-01T12:00:00+00:00".parse::<Record>().unwrap();
+    let rec = |tags: &[&str], note: &str| Record {
+      tags: tags.iter().map(|&tag| tag.into()).collect(),
+      note: Some(note.into()),
+      ..time.clone()
+    };
+    let valid = rec(&["leg", "##day"], "a // b");
+    valid.validate().unwrap();
+    assert_eq!(valid, valid.to_string().parse().unwrap());
+    assert!(rec(&["leg day"], "hi").validate().is_err());
+    assert!(rec(&[""], "hi").validate().is_err());
+    assert!(rec(&["a//b"], "hi").validate().is_err());
+    assert!(rec(&["leg"], "hi\nthere").validate().is_err());
+    assert!(time.with_details("# x2").is_err());
+  }
+
+  #[test]
+  fn should_parse_absolute_and_relative_dates() {
+    let today = NaiveDate::from_ymd_opt(2024, 3, 31).unwrap();
+    let date = |input| parse_date(today, input).ok();
+    let ymd = |y, m, d| NaiveDate::from_ymd_opt(y, m, d);
+    assert_eq!(ymd(2023, 12, 1), date("2023-12-01"));
+    assert_eq!(ymd(2024, 3, 28), date("-3d"));
+    assert_eq!(ymd(2024, 3, 17), date(" -2w "));
+    assert_eq!(ymd(2024, 2, 29), date("-1m"));
+    assert_eq!(ymd(2025, 3, 31), date("+1y"));
+    assert_eq!(None, date("2w"));
+    assert_eq!(None, date("-2é"));
+    assert_eq!(None, date("-262144-01-05"));
+    assert_eq!(None, date("0000-12-31"));
+    assert_eq!(None, date("-2025y"));
+    assert_eq!(None, date("+9000000d"));
+  }
+
+  #[test]
+  fn should_keep_times_unique() {
+    let date = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap();
+    let noon = parse_time(date, "12:00").unwrap();
+    let recs =
+      [Record::new(noon), Record::new(noon + Duration::seconds(1))];
+    assert_eq!(noon + Duration::seconds(2), unique_time(&recs, noon));
+    assert!(parse_time(date, "25:00").is_err());
+  }
+}
@@ -1,6 +1,6 @@
 use std::cmp::min;
 
-#[derive(Default, Clone, PartialEq)]
+#[derive(Clone, PartialEq)]
 pub struct SelectableList<T> {
   items: Vec<T>,
   selected: usize,
@@ -46,6 +46,16 @@ impl<T> SelectableList<T> {
     self.selected = min(selected, self.last_index());
   }
 
+  /// Selects item at `index`, counted from the first one even when
+  /// the selection is reversed
+  pub fn select_index(&mut self, index: usize) {
+    if self.reversed_selection {
+      self.select(self.last_index().saturating_sub(index));
+    } else {
+      self.select(index);
+    }
+  }
+
   pub fn select_prev(&mut self) {
     if self.reversed_selection {
       self._select_next();
@@ -69,9 +79,11 @@ impl<T> SelectableList<T> {
   fn _select_next(&mut self) {
     self.select(self._selected().saturating_add(1));
   }
+}
 
-  pub fn wrapping_select_next(&mut self) {
-    self.selected = self.selected.wrapping_add(1) % self.len();
+impl<T> Default for SelectableList<T> {
+  fn default() -> Self {
+    Self::new()
   }
 }
 
@@ -1,29 +1,71 @@
 mod days_frame;
 
-use std::{collections::HashMap, ops::Not};
+use std::{cmp::min, collections::HashMap, fmt};
 
 use anyhow::{Context, Result};
 use chrono::{
   DateTime, Datelike, Days, Duration, Local, Month, NaiveDate,
-  Timelike,
+  Timelike, Weekday,
 };
-use futures::future::try_join_all;
+use ratatui::style::Color;
 use tokio::sync::watch;
 
 use self::days_frame::DaysFrame;
 
-use super::{level::Level, selectable_list::SelectableList, Journal};
+use super::{
+  history::{Action, Entry, History},
+  journal::Journal,
+  level::Level,
+  period::Period,
+  record::{unique_time, Record},
+  selectable_list::SelectableList,
+};
 
 pub type Hour = u8;
 
+/// Failure after the action was written to the journal, retried by
+/// [`Tab::finish`] rather than by applying the action again
+#[derive(Debug)]
+pub struct Applied(pub Action);
+
+impl fmt::Display for Applied {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "journal changed")
+  }
+}
+
 #[derive(Default, Clone, PartialEq)]
 pub struct State {
   pub date: NaiveDate,
-  pub list: SelectableList<DateTime<Local>>,
+  pub list: SelectableList<Record>,
   pub level: Level,
   pub recs_by_hour: HashMap<Hour, usize>,
   pub recs_by_date: Vec<(NaiveDate, usize)>,
   pub recs_by_month: HashMap<Month, usize>,
+  /// History entries from the newest, flagged if undone
+  pub history: Vec<(Entry, bool)>,
+  /// Count of lines of the day skipped as malformed
+  pub malformed: usize,
+  /// Records count per day from the first day of the heatmap to
+  /// today, resolved on demand
+  pub recs_by_day: Vec<(NaiveDate, usize)>,
+  /// Span records are shown and counted by
+  pub period: Period,
+  /// Records count per day of the period, listed instead of records
+  /// unless the period is a day
+  pub totals: Vec<(NaiveDate, usize)>,
+}
+
+/// Days shown in the days chart until the size is known
+pub const DEFAULT_DAYS: u64 = 10;
+
+/// Weeks shown in the heatmap, up to the current one
+pub const HEATMAP_WEEKS: u64 = 53;
+
+/// Monday starting the heatmap ending at the week of `today`
+pub fn heatmap_start(today: NaiveDate) -> NaiveDate {
+  let monday = today.week(Weekday::Mon).first_day();
+  monday - Days::new((HEATMAP_WEEKS - 1) * 7)
 }
 
 impl State {
@@ -36,30 +78,13 @@ impl State {
   }
 }
 
-#[derive(Clone)]
-enum Action {
-  Add(DateTime<Local>),
-  Delete(DateTime<Local>),
-}
-
-impl Not for Action {
-  type Output = Self;
-
-  fn not(self) -> Self::Output {
-    match self {
-      Action::Add(dt) => Action::Delete(dt),
-      Action::Delete(dt) => Action::Add(dt),
-    }
-  }
-}
-
 pub struct Tab {
   title: String,
+  color: Option<Color>,
   target: usize,
   journal: Box<dyn Journal>,
   state: State,
-  undoes: Vec<Action>,
-  redoes: Vec<Action>,
+  history: History,
   days_frame: DaysFrame,
   state_tx: watch::Sender<State>,
 }
@@ -78,16 +103,26 @@ impl Tab {
 
     Self {
       title: title.into(),
+      color: None,
       target,
       journal,
       state,
-      undoes: vec![],
-      redoes: vec![],
-      days_frame: DaysFrame::new(today, Days::new(10)),
+      history: History::default(),
+      days_frame: DaysFrame::new(today, Days::new(DEFAULT_DAYS)),
       state_tx,
     }
   }
 
+  pub fn with_color(mut self, color: Option<Color>) -> Self {
+    self.color = color;
+    self
+  }
+
+  pub fn with_history(mut self, history: History) -> Self {
+    self.history = history;
+    self
+  }
+
   pub async fn resolve_all(&mut self) -> Result<()> {
     self.resolve().await?;
     self.state.recs_by_month = self.recs_by_month().await?;
@@ -97,29 +132,79 @@ impl Tab {
 
   async fn resolve(&mut self) -> Result<()> {
     self.state.date = self.days_frame.cur;
+    self.state.history = self
+      .history
+      .entries()
+      .map(|(entry, undone)| (entry.clone(), undone))
+      .collect();
     self.emit_changes()?;
-    *self.state.list =
-      self.journal.day_records(self.state.date).await?;
+    let (date, period) = (self.state.date, self.state.period);
+    let (recs, malformed) =
+      self.journal.day_with_malformed(date).await?;
+    self.state.malformed = malformed;
+    if period == Period::Day {
+      *self.state.list = recs;
+      self.state.totals.clear();
+    } else {
+      self.state.list.clear();
+      self.state.totals =
+        self.recs_for(period.start(date), period.end(date)).await?;
+    }
     self.emit_changes()?;
     self.state.level = self.level().await?;
     self.emit_changes()?;
     self.state.recs_by_hour = self.recs_by_hour().await?;
     self.emit_changes()?;
-    self.state.recs_by_date = self
-      .recs_for(self.days_frame.start, self.days_frame.end)
-      .await?;
+    self.state.recs_by_date = self.recs_by_date().await?;
     self.emit_changes()?;
     Ok(())
   }
 
+  /// Records count per day of the frame or per period around the
+  /// date, as many periods as days in the frame
+  async fn recs_by_date(&self) -> Result<Vec<(NaiveDate, usize)>> {
+    let period = self.state.period;
+    if period == Period::Day {
+      return self
+        .recs_for(self.days_frame.start, self.days_frame.end)
+        .await;
+    }
+    let today = Local::now().date_naive();
+    let len = self.days_frame.len() as i32;
+    let end = min(
+      period.shift(self.state.date, len / 2),
+      period.start(today),
+    );
+    self.recs_by_period(period.shift(end, 1 - len), len).await
+  }
+
+  /// Records count of `n` periods from the one containing `first`,
+  /// keyed by their first days
+  async fn recs_by_period(
+    &self,
+    first: NaiveDate,
+    n: i32,
+  ) -> Result<Vec<(NaiveDate, usize)>> {
+    let period = self.state.period;
+    if n < 1 {
+      return Ok(vec![]);
+    }
+    let (start, last) =
+      (period.start(first), period.shift(first, n - 1));
+    let counts = self.recs_for(start, period.end(last)).await?;
+    Ok(period.group(first, n, counts))
+  }
+
+  /// Records amount per hour of the day or of the whole period
   async fn recs_by_hour(&self) -> Result<HashMap<Hour, usize>> {
+    let (date, period) = (self.state.date, self.state.period);
     let recs = self
       .journal
-      .day_records(self.state.date)
+      .records_between(period.start(date), period.end(date))
       .await?
       .into_iter()
-      .fold(HashMap::new(), |mut map, dt| {
-        *map.entry(dt.time().hour() as _).or_default() += 1;
+      .fold(HashMap::new(), |mut map, rec| {
+        *map.entry(rec.time.hour() as _).or_default() += rec.amount();
         map
       });
     Ok(recs)
@@ -144,37 +229,49 @@ impl Tab {
     start: NaiveDate,
     end: NaiveDate,
   ) -> Result<Vec<(NaiveDate, usize)>> {
-    let recs = start.iter_days().take_while(|date| date <= &end).map(
-      |date| async move {
-        Ok((date, self.journal.day_records(date).await?.len()))
-      },
-    );
-    try_join_all(recs).await
+    self.journal.count_by_day(start, end).await
   }
 
+  /// Level of the day against the previous week, or of the period
+  /// against the previous four
   async fn level(&self) -> Result<Level> {
-    let today = self.state.date;
-    let recent_days = self
-      .recs_for(today - Days::new(7), today - Days::new(1))
-      .await?;
+    let (date, period) = (self.state.date, self.state.period);
+    let (date_count, recent_days) = if period == Period::Day {
+      let count = self
+        .journal
+        .day_records(date)
+        .await?
+        .iter()
+        .map(Record::amount)
+        .sum();
+      let recent = self
+        .recs_for(date - Days::new(7), date - Days::new(1))
+        .await?;
+      (count, recent)
+    } else {
+      let count = self.state.totals.iter().map(|(_, n)| n).sum();
+      let recent =
+        self.recs_by_period(period.shift(date, -4), 4).await?;
+      (count, recent)
+    };
     let recent_days_iter =
       recent_days.iter().map(|(_, recs)| *recs as f32);
     let recent_days_sum: f32 = recent_days_iter.clone().sum();
     let recent_days_count = recent_days_iter
       .filter(|count| (count - 0.).abs() > f32::EPSILON)
       .count();
-    let date_count =
-      self.journal.day_records(self.state.date).await?.len();
     let middle = if recent_days_count == 0 {
       date_count as f32
     } else {
       recent_days_sum / recent_days_count as f32
     };
-    Ok(Level::new(date_count, middle, self.target))
+    let target = self.target * period.days(date);
+    Ok(Level::new(date_count, middle, target))
   }
 
   fn emit_changes(&self) -> Result<()> {
-    self.state_tx.send(self.state.clone())?;
+    // Tab may have no subscribers, e.g. in CLI
+    self.state_tx.send_replace(self.state.clone());
     Ok(())
   }
 
@@ -182,22 +279,123 @@ impl Tab {
     &self.title
   }
 
+  pub fn set_title(&mut self, title: impl Into<String>) {
+    self.title = title.into();
+  }
+
+  pub fn color(&self) -> Option<Color> {
+    self.color
+  }
+
+  pub fn state(&self) -> &State {
+    &self.state
+  }
+
   pub fn subscribe(&self) -> watch::Receiver<State> {
     self.state_tx.subscribe()
   }
 
+  /// Signals about external changes of the journal, if supported
+  pub fn watch_journal(&self) -> Result<Option<watch::Receiver<()>>> {
+    self.journal.watch()
+  }
+
   pub async fn prev_date(&mut self) -> Result<()> {
-    self.days_frame.prev();
+    match self.state.period {
+      Period::Day => self.days_frame.prev(),
+      period => {
+        self.days_frame.jump_to(period.shift(self.state.date, -1));
+      }
+    }
     self.resolve().await?;
     Ok(())
   }
 
   pub async fn next_date(&mut self) -> Result<()> {
-    self.days_frame.next();
+    match self.state.period {
+      Period::Day => self.days_frame.next(),
+      period => {
+        self.days_frame.jump_to(period.shift(self.state.date, 1));
+      }
+    }
+    self.resolve().await?;
+    Ok(())
+  }
+
+  /// Shows `days` in the days chart, keeping the date
+  pub fn resize_days(&mut self, days: u64) {
+    self.days_frame.resize(days);
+  }
+
+  /// Resolves the month counts too, as resizing may abort the
+  /// resolution of a newly opened or selected tab
+  pub async fn set_days(&mut self, days: u64) -> Result<()> {
+    self.resize_days(days);
+    self.resolve_all().await
+  }
+
+  /// Switches between day, week and month views
+  pub async fn next_period(&mut self) -> Result<()> {
+    self.state.period = self.state.period.next();
+    self.resolve().await?;
+    Ok(())
+  }
+
+  pub async fn resolve_heatmap(&mut self) -> Result<()> {
+    let today = Local::now().date_naive();
+    self.state.recs_by_day =
+      self.recs_for(heatmap_start(today), today).await?;
+    self.emit_changes()
+  }
+
+  pub async fn jump_to(&mut self, date: NaiveDate) -> Result<()> {
+    self.days_frame.jump_to(date);
+    self.resolve().await?;
+    Ok(())
+  }
+
+  pub async fn prev_frame(&mut self) -> Result<()> {
+    match self.state.period {
+      Period::Day => self.days_frame.prev_frame(),
+      period => self.shift_periods(period, -1),
+    }
+    self.resolve().await?;
+    Ok(())
+  }
+
+  pub async fn next_frame(&mut self) -> Result<()> {
+    match self.state.period {
+      Period::Day => self.days_frame.next_frame(),
+      period => self.shift_periods(period, 1),
+    }
     self.resolve().await?;
     Ok(())
   }
 
+  /// Moves selection by `frames` chart widths of periods
+  fn shift_periods(&mut self, period: Period, frames: i32) {
+    let n = frames * self.days_frame.len() as i32;
+    self.days_frame.jump_to(period.shift(self.state.date, n));
+  }
+
+  pub async fn select_date(&mut self, date: NaiveDate) -> Result<()> {
+    match self.state.period {
+      Period::Day => self.days_frame.select(date),
+      _ => self.days_frame.jump_to(date),
+    }
+    self.resolve().await?;
+    Ok(())
+  }
+
+  pub fn select_record(&mut self, i: usize) -> Result<()> {
+    let s = self.state.list.selected();
+    self.state.list.select_index(i);
+    if s != self.state.list.selected() {
+      self.emit_changes()?;
+    }
+    Ok(())
+  }
+
   pub fn prev_selection(&mut self) -> Result<()> {
     let s = self.state.list.selected();
     self.state.list.select_prev();
@@ -216,24 +414,102 @@ impl Tab {
     Ok(())
   }
 
-  pub async fn add_record(&mut self) -> Result<()> {
-    let action = Action::Add(Local::now());
+  pub async fn add_record(&mut self) -> Result<Option<Action>> {
+    self.add_record_at(Local::now()).await
+  }
+
+  pub async fn add_record_at(
+    &mut self,
+    time: DateTime<Local>,
+  ) -> Result<Option<Action>> {
+    let recs = self.journal.day_records(time.date_naive()).await?;
+    let rec = Record::new(unique_time(&recs, time));
+    self.perform(Action::Add(rec)).await
+  }
+
+  /// Replaces details of the selected record with parsed `details`
+  pub async fn edit_selected_record(
+    &mut self,
+    details: &str,
+  ) -> Result<Option<Action>> {
+    let Some(rec) = self.state.list.selected_item().cloned() else {
+      return Ok(None);
+    };
+    let edited = rec.clone().with_details(details)?;
+    if edited == rec {
+      return Ok(None);
+    }
+    self.perform(Action::Edit(rec, edited)).await
+  }
+
+  /// Moves the selected record to `time`, keeping its details
+  pub async fn move_selected_record(
+    &mut self,
+    time: DateTime<Local>,
+  ) -> Result<Option<Action>> {
+    let Some(rec) = self.state.list.selected_item().cloned() else {
+      return Ok(None);
+    };
+    let others: Vec<_> = self
+      .journal
+      .day_records(time.date_naive())
+      .await?
+      .into_iter()
+      .filter(|other| other != &rec)
+      .collect();
+    let time = unique_time(&others, time);
+    if time == rec.time {
+      return Ok(None);
+    }
+    let moved = Record {
+      time,
+      ..rec.clone()
+    };
+    self.perform(Action::Move(rec, moved)).await
+  }
+
+  /// Applies the action and keeps it in history, returning it back
+  async fn perform(
+    &mut self,
+    action: Action,
+  ) -> Result<Option<Action>> {
     self.apply(&action).await?;
-    self.undoes.push(!action);
-    self.redoes.clear();
-    self.resolve().await?;
-    Ok(())
+    self.history.push(action.clone());
+    self.finish().await.context(Applied(action.clone()))?;
+    Ok(Some(action))
+  }
+
+  /// Saves history and resolves the tab again after a change
+  pub async fn finish(&mut self) -> Result<()> {
+    self.history.save().await?;
+    self.resolve().await
   }
 
   async fn apply(&mut self, action: &Action) -> Result<()> {
-    match *action {
-      Action::Add(dt) => {
-        self.journal.add(dt).await?;
-        self.increment_month_counter(dt, 1);
+    match action {
+      Action::Add(rec) => {
+        self.journal.add(rec.clone()).await?;
+        self.increment_month_counter(rec.time, rec.amount() as _);
+      }
+      Action::Delete(rec) => {
+        self.journal.remove(rec.time).await?;
+        self.increment_month_counter(
+          rec.time,
+          -(rec.amount() as isize),
+        );
       }
-      Action::Delete(dt) => {
-        self.journal.remove(dt).await?;
-        self.increment_month_counter(dt, -1);
+      Action::Edit(from, to) => {
+        self.journal.update(to.clone()).await?;
+        let dv = to.amount() as isize - from.amount() as isize;
+        self.increment_month_counter(to.time, dv);
+      }
+      Action::Move(from, to) => {
+        self.journal.move_record(from.time, to.clone()).await?;
+        self.increment_month_counter(
+          from.time,
+          -(from.amount() as isize),
+        );
+        self.increment_month_counter(to.time, to.amount() as _);
       }
     }
     Ok(())
@@ -256,37 +532,42 @@ impl Tab {
       .or_default()
   }
 
-  pub async fn delete_selected_record(&mut self) -> Result<()> {
-    if let Some(&dt) = self.state.list.selected_item() {
-      let action = Action::Delete(dt);
-      self.apply(&action).await?;
-      self.undoes.push(!action);
-      self.redoes.clear();
-      self.resolve().await?;
+  pub async fn delete_selected_record(
+    &mut self,
+  ) -> Result<Option<Action>> {
+    match self.state.list.selected_item() {
+      Some(rec) => self.perform(Action::Delete(rec.clone())).await,
+      None => Ok(None),
     }
-    Ok(())
   }
 
-  pub async fn undo(&mut self) -> Result<()> {
-    if let Some(action) = self.undoes.pop() {
-      self.apply(&action).await?;
-      self.redoes.push(!action);
-      self.resolve().await?;
-    }
-    Ok(())
+  /// Reverts the last action, returning it
+  pub async fn undo(&mut self) -> Result<Option<Action>> {
+    let Some(action) = self.history.last_undo().cloned() else {
+      return Ok(None);
+    };
+    self.apply(&!action.clone()).await?;
+    self.history.undo();
+    self.finish().await.context(Applied(action.clone()))?;
+    Ok(Some(action))
   }
 
-  pub async fn redo(&mut self) -> Result<()> {
-    if let Some(action) = self.redoes.pop() {
-      self.apply(&action).await?;
-      self.undoes.push(!action);
-      self.resolve().await?;
-    }
-    Ok(())
+  /// Performs the last undone action again, returning it
+  pub async fn redo(&mut self) -> Result<Option<Action>> {
+    let Some(action) = self.history.last_redo().cloned() else {
+      return Ok(None);
+    };
+    self.apply(&action).await?;
+    self.history.redo();
+    self.finish().await.context(Applied(action.clone()))?;
+    Ok(Some(action))
   }
 
   pub fn demount(&mut self) -> Result<()> {
-    self.state = State::new(self.state.date);
+    self.state = State {
+      period: self.state.period,
+      ..State::new(self.state.date)
+    };
     self.emit_changes()?;
     Ok(())
   }
@@ -36,6 +36,44 @@ impl DaysFrame {
     }
   }
 
+  /// Centres the frame on `date`, keeping it from passing today
+  pub fn jump_to(&mut self, date: NaiveDate) {
+    let today = Local::now().date_naive();
+    let len = self.len();
+    self.cur = min(date, today);
+    self.end = min(self.cur + Days::new(len / 2), today);
+    self.start = self.end - Days::new(len - 1);
+  }
+
+  /// Moves selection back by the frame width
+  pub fn prev_frame(&mut self) {
+    self.jump_to(self.cur - Days::new(self.len()));
+  }
+
+  /// Moves selection forward by the frame width
+  pub fn next_frame(&mut self) {
+    self.jump_to(self.cur + Days::new(self.len()));
+  }
+
+  /// Selects `date` if it's in the frame and not in the future
+  pub fn select(&mut self, date: NaiveDate) {
+    let today = Local::now().date_naive();
+    if (self.start..=self.end).contains(&date) {
+      self.cur = min(date, today);
+    }
+  }
+
+  /// Changes the frame to `len` days around the current date
+  pub fn resize(&mut self, len: u64) {
+    assert!(len > 1, "invalid days count");
+    self.start = self.end - Days::new(len - 1);
+    self.jump_to(self.cur);
+  }
+
+  pub fn len(&self) -> u64 {
+    self.end.signed_duration_since(self.start).num_days() as u64 + 1
+  }
+
   fn middle(&self) -> NaiveDate {
     let days_range =
       self.end.signed_duration_since(self.start).num_days() as u64;
@@ -55,4 +93,41 @@ mod tests {
     df.next();
     assert_eq!(today, df.cur);
   }
+
+  #[test]
+  fn should_recentre_on_jump() {
+    let today = Local::now().date_naive();
+    let mut df = DaysFrame::new(today, Days::new(10));
+    df.jump_to(today - Days::new(90));
+    assert_eq!(today - Days::new(90), df.cur);
+    assert_eq!(today - Days::new(85), df.end);
+    assert_eq!(today - Days::new(94), df.start);
+    df.next_frame();
+    assert_eq!(today - Days::new(80), df.cur);
+    df.jump_to(today + Days::new(3));
+    assert_eq!((today, today), (df.cur, df.end));
+  }
+
+  #[test]
+  fn should_keep_date_on_resize() {
+    let today = Local::now().date_naive();
+    let mut df = DaysFrame::new(today, Days::new(10));
+    df.jump_to(today - Days::new(40));
+    df.resize(30);
+    assert_eq!(30, df.len());
+    assert_eq!(today - Days::new(40), df.cur);
+    assert_eq!(today - Days::new(25), df.end);
+    df.resize(4);
+    assert_eq!((4, today - Days::new(38)), (df.len(), df.end));
+  }
+
+  #[test]
+  fn should_select_only_dates_in_frame() {
+    let today = Local::now().date_naive();
+    let mut df = DaysFrame::new(today, Days::new(5));
+    df.select(today - Days::new(4));
+    assert_eq!(today - Days::new(4), df.cur);
+    df.select(today - Days::new(5));
+    assert_eq!(today - Days::new(4), df.cur);
+  }
 }
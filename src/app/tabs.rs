@@ -0,0 +1,291 @@
+use std::sync::Arc;
+
+use anyhow::{ensure, Context, Result};
+use tokio::sync::Mutex;
+
+use super::{
+  history::History,
+  prompt::{Prompt, PromptKind},
+  show_status,
+  tab::Tab,
+  App, State, TabTitle,
+};
+use crate::cfg::{self, Config};
+
+/// Opens the tab configured by `tab`, with a warning to show when
+/// its history can't be loaded
+pub fn open(
+  cfg: &Config,
+  tab: &cfg::Tab,
+) -> Result<(Tab, Option<String>)> {
+  let journal = cfg.journal(tab)?;
+  let (history, err) =
+    History::load_or_default(&cfg.history_path(tab));
+  let warning = err.map(|err| {
+    format!("{}: {err}, history won't be saved", tab.title)
+  });
+  let opened = Tab::new(&tab.title, tab.target, journal)
+    .with_color(tab.color)
+    .with_history(history);
+  Ok((opened, warning))
+}
+
+impl App {
+  pub(super) fn push_tab(&mut self, mut tab: Tab) -> Result<()> {
+    tab.resize_days(self.days);
+    let id = self.next_tab_id;
+    self.next_tab_id += 1;
+    let title = TabTitle {
+      id,
+      text: tab.title().clone(),
+      color: tab.color(),
+    };
+    self.modify_state(|state| state.tabs.push(title))?;
+    self.subscribe_on_tab(id, &tab);
+    let tab = Arc::new(Mutex::new(tab));
+    self.watch_journal(id, &tab);
+    self.tabs.push(tab);
+    Ok(())
+  }
+
+  pub(super) fn new_tab(&mut self) -> Result<()> {
+    self.set_prompt(Some(Prompt::new(PromptKind::NewTabTitle, "")))
+  }
+
+  pub(super) fn rename_tab(&mut self) -> Result<()> {
+    let title = self.cfg.tabs[self.tabs.selected()].title.clone();
+    self.set_prompt(Some(Prompt::new(PromptKind::RenameTab, title)))
+  }
+
+  pub(super) fn archive_tab(&mut self) -> Result<()> {
+    self.set_prompt(Some(Prompt::new(PromptKind::ArchiveTab, "")))
+  }
+
+  /// Moves the active tab by `offset` positions, saving the order
+  pub(super) fn move_tab(&mut self, offset: isize) -> Result<()> {
+    let i = self.tabs.selected();
+    let Some(j) = i
+      .checked_add_signed(offset)
+      .filter(|j| *j < self.tabs.len())
+    else {
+      return Ok(());
+    };
+    if let Err(err) = self.save_cfg(|cfg| {
+      cfg.tabs.swap(i, j);
+      Ok(())
+    }) {
+      let message = format!("{err:#}");
+      return self.modify_state(|state| state.error = Some(message));
+    }
+    self.tabs.swap(i, j);
+    self.tabs.select(j);
+    self.modify_state(|state| {
+      state.tabs.swap(i, j);
+      state.tabs.select(j);
+    })
+  }
+
+  /// Applies the tab prompt, keeping it open with the error on
+  /// failure
+  pub(super) fn submit_tab_prompt(
+    &mut self,
+    prompt: Prompt,
+  ) -> Result<()> {
+    match self.apply_tab_prompt(prompt) {
+      Ok(next) => self.set_prompt(next),
+      Err(err) => {
+        self.modify_prompt(|p| p.error = Some(format!("{err:#}")))
+      }
+    }
+  }
+
+  /// Returns the next step of the dialog if any
+  fn apply_tab_prompt(
+    &mut self,
+    prompt: Prompt,
+  ) -> Result<Option<Prompt>> {
+    let input = prompt.input.trim();
+    match prompt.kind {
+      PromptKind::NewTabTitle => {
+        ensure!(!input.is_empty(), "title can't be empty");
+        ensure!(
+          !self.has_title(input, None),
+          "tab `{input}` already exists"
+        );
+        let kind = PromptKind::NewTabTarget {
+          title: input.to_owned(),
+        };
+        Ok(Some(Prompt::new(kind, "1")))
+      }
+      PromptKind::NewTabTarget { title } => {
+        let target =
+          input.parse().context("target must be a number")?;
+        let dir = title.to_lowercase();
+        let kind = PromptKind::NewTabDir { title, target };
+        Ok(Some(Prompt::new(kind, dir)))
+      }
+      PromptKind::NewTabDir { title, target } => {
+        ensure!(!input.is_empty(), "directory can't be empty");
+        let mut tab = cfg::Tab::new(&title, target);
+        if input != title.to_lowercase() {
+          tab.dir = Some(input.into());
+        }
+        self.create_tab(tab)?;
+        Ok(None)
+      }
+      PromptKind::RenameTab => {
+        self.rename_active_tab(input)?;
+        Ok(None)
+      }
+      PromptKind::ArchiveTab => {
+        if input.eq_ignore_ascii_case("y") {
+          self.archive_active_tab()?;
+        }
+        Ok(None)
+      }
+      PromptKind::SwitchTab => {
+        let i = self
+          .state()
+          .matching_tabs(input)
+          .first()
+          .map(|(i, _)| *i)
+          .context("no matching tab")?;
+        self.select_tab(i)?;
+        Ok(None)
+      }
+      // Submitted by `App::submit_prompt`
+      PromptKind::AddRecordAt
+      | PromptKind::EditRecord
+      | PromptKind::MoveRecord
+      | PromptKind::GoToDate => Ok(None),
+    }
+  }
+
+  fn create_tab(&mut self, tab: cfg::Tab) -> Result<()> {
+    let mut opened = None;
+    self.save_cfg(|cfg| {
+      cfg.tabs.push(tab.clone());
+      cfg.validate()?;
+      opened = Some(open(cfg, &tab)?);
+      Ok(())
+    })?;
+    let (opened, warning) = opened.expect("opened tab");
+    self.push_tab(opened)?;
+    self.select_tab(self.tabs.len() - 1)?;
+    if let Some(warning) = warning {
+      tokio::spawn(show_status(self.state_tx.clone(), warning));
+    }
+    Ok(())
+  }
+
+  fn rename_active_tab(&mut self, title: &str) -> Result<()> {
+    let i = self.tabs.selected();
+    ensure!(!title.is_empty(), "title can't be empty");
+    ensure!(
+      !self.has_title(title, Some(i)),
+      "tab `{title}` already exists"
+    );
+    self.save_cfg(|cfg| {
+      let tab = &mut cfg.tabs[i];
+      // Keeps the data where it is when the default dir changes
+      if tab.dir.is_none()
+        && tab.title.to_lowercase() != title.to_lowercase()
+      {
+        tab.dir = Some(tab.title.to_lowercase().into());
+      }
+      tab.title = title.to_owned();
+      Ok(())
+    })?;
+    self
+      .modify_state(|state| state.tabs[i].text = title.to_owned())?;
+    let tab = self.tabs[i].clone();
+    let title = title.to_owned();
+    tokio::spawn(async move { tab.lock().await.set_title(title) });
+    Ok(())
+  }
+
+  /// Removes the active tab from config, keeping its journal
+  fn archive_active_tab(&mut self) -> Result<()> {
+    let i = self.tabs.selected();
+    ensure!(self.tabs.len() > 1, "the last tab can't be archived");
+    self.save_cfg(|cfg| {
+      cfg.tabs.remove(i);
+      Ok(())
+    })?;
+    self.spawn_tab_blocking(|tab| async move {
+      tab.lock().await.demount()
+    });
+    self.tabs.remove(i);
+    let id = self.state().tabs[i].id;
+    self.modify_state(|state| {
+      state.tabs.remove(i);
+    })?;
+    self.log.lock().expect("poisoned log").forget(id);
+    self.show_tab(i.min(self.tabs.len() - 1))
+  }
+
+  fn has_title(&self, title: &str, except: Option<usize>) -> bool {
+    let title = title.to_lowercase();
+    self.cfg.tabs.iter().enumerate().any(|(i, tab)| {
+      Some(i) != except && tab.title.to_lowercase() == title
+    })
+  }
+
+  /// Modifies a copy of the config, replacing the current one once
+  /// it's valid and saved
+  fn save_cfg(
+    &mut self,
+    f: impl FnOnce(&mut Config) -> Result<()>,
+  ) -> Result<()> {
+    let mut cfg = self.cfg.clone();
+    f(&mut cfg)?;
+    cfg.validate()?;
+    cfg.save()?;
+    self.cfg = cfg;
+    Ok(())
+  }
+}
+
+impl State {
+  /// Tabs with their indices matching `query` fuzzily, best first
+  pub fn matching_tabs(
+    &self,
+    query: &str,
+  ) -> Vec<(usize, &TabTitle)> {
+    let mut matches: Vec<_> = self
+      .tabs
+      .iter()
+      .enumerate()
+      .filter_map(|(i, tab)| {
+        Some((fuzzy_score(query, &tab.text)?, i, tab))
+      })
+      .collect();
+    matches.sort_by_key(|(score, i, _)| (*score, *i));
+    matches.into_iter().map(|(_, i, tab)| (i, tab)).collect()
+  }
+}
+
+/// Score of `text` containing chars of `query` in order, lower is
+/// better: skipped chars count against it
+fn fuzzy_score(query: &str, text: &str) -> Option<usize> {
+  let mut text = text.chars().flat_map(char::to_lowercase);
+  let mut score = 0;
+  for q in query.chars().flat_map(char::to_lowercase) {
+    score += text.by_ref().position(|c| c == q)?;
+  }
+  Some(score)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn should_rank_fuzzy_matches() {
+    assert_eq!(Some(0), fuzzy_score("", "Smokes"));
+    assert_eq!(Some(0), fuzzy_score("sm", "Smokes"));
+    assert_eq!(Some(4), fuzzy_score("ss", "Smokes"));
+    assert_eq!(None, fuzzy_score("sx", "Smokes"));
+    assert!(fuzzy_score("tr", "Trains") < fuzzy_score("tr", "Water"));
+  }
+}
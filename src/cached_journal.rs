@@ -0,0 +1,240 @@
+use std::{
+  collections::HashMap,
+  sync::{Mutex, MutexGuard},
+};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::{DateTime, Local, NaiveDate};
+use futures::future::try_join_all;
+use tokio::sync::watch;
+
+use crate::app::{
+  journal::{days, DayRecords, Journal, Stamp},
+  record::Record,
+};
+
+/// Keeps day records of the inner journal in memory. Cached days are
+/// validated with [`Journal::stamps_between`] to notice external
+/// changes
+pub struct CachedJournal<J: Journal> {
+  inner: J,
+  days: Mutex<HashMap<NaiveDate, CachedDay>>,
+}
+
+struct CachedDay {
+  recs: DayRecords,
+  malformed: usize,
+  stamp: Option<Stamp>,
+}
+
+impl<J: Journal> CachedJournal<J> {
+  pub fn new(inner: J) -> Self {
+    Self {
+      inner,
+      days: Mutex::default(),
+    }
+  }
+
+  pub fn invalidate(&self, date: NaiveDate) {
+    self.days().remove(&date);
+  }
+
+  fn days(&self) -> MutexGuard<'_, HashMap<NaiveDate, CachedDay>> {
+    self.days.lock().expect("poisoned cache")
+  }
+
+  /// Fetches days which are missing in cache or outdated
+  async fn refresh(
+    &self,
+    start: NaiveDate,
+    end: NaiveDate,
+  ) -> Result<()> {
+    let stamps = self.inner.stamps_between(start, end).await?;
+    let stamp =
+      |date| stamps.as_ref().and_then(|s| s.get(&date).copied());
+    let stale: Vec<_> = {
+      let cache = self.days();
+      days(start, end)
+        .filter(|date| {
+          cache.get(date).is_none_or(|day| {
+            stamps.is_some() && day.stamp != stamp(*date)
+          })
+        })
+        .collect()
+    };
+    // Read day by day to keep records where the inner journal has
+    // them, even when their time is of another day
+    let read = stale
+      .iter()
+      .map(|&date| self.inner.day_with_malformed(date));
+    let read = try_join_all(read).await?;
+    let mut cache = self.days();
+    for (date, (recs, malformed)) in stale.into_iter().zip(read) {
+      cache.insert(
+        date,
+        CachedDay {
+          recs,
+          malformed,
+          stamp: stamp(date),
+        },
+      );
+    }
+    Ok(())
+  }
+}
+
+#[async_trait]
+impl<J: Journal> Journal for CachedJournal<J> {
+  async fn day_records(&self, date: NaiveDate) -> Result<DayRecords> {
+    self.records_between(date, date).await
+  }
+
+  async fn add(&self, rec: Record) -> Result<()> {
+    let date = rec.time.date_naive();
+    let res = self.inner.add(rec).await;
+    self.invalidate(date);
+    res
+  }
+
+  async fn remove(&self, dt: DateTime<Local>) -> Result<()> {
+    let res = self.inner.remove(dt).await;
+    self.invalidate(dt.date_naive());
+    res
+  }
+
+  async fn update(&self, rec: Record) -> Result<()> {
+    let date = rec.time.date_naive();
+    let res = self.inner.update(rec).await;
+    self.invalidate(date);
+    res
+  }
+
+  async fn move_record(
+    &self,
+    from: DateTime<Local>,
+    to: Record,
+  ) -> Result<()> {
+    let date = to.time.date_naive();
+    let res = self.inner.move_record(from, to).await;
+    self.invalidate(from.date_naive());
+    self.invalidate(date);
+    res
+  }
+
+  async fn records_between(
+    &self,
+    start: NaiveDate,
+    end: NaiveDate,
+  ) -> Result<DayRecords> {
+    self.refresh(start, end).await?;
+    let cache = self.days();
+    Ok(
+      days(start, end)
+        .filter_map(|date| cache.get(&date))
+        .flat_map(|day| day.recs.iter().cloned())
+        .collect(),
+    )
+  }
+
+  async fn stamps_between(
+    &self,
+    start: NaiveDate,
+    end: NaiveDate,
+  ) -> Result<Option<HashMap<NaiveDate, Stamp>>> {
+    self.inner.stamps_between(start, end).await
+  }
+
+  fn watch(&self) -> Result<Option<watch::Receiver<()>>> {
+    self.inner.watch()
+  }
+
+  async fn day_with_malformed(
+    &self,
+    date: NaiveDate,
+  ) -> Result<(DayRecords, usize)> {
+    self.refresh(date, date).await?;
+    let cached = self
+      .days()
+      .get(&date)
+      .map(|day| (day.recs.clone(), day.malformed));
+    // Invalidated by a write since the refresh
+    match cached {
+      Some(cached) => Ok(cached),
+      None => self.inner.day_with_malformed(date).await,
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::path::Path;
+
+  use chrono::{Duration, TimeZone};
+
+  use crate::{fs_journal::FsJournal, sqlite_journal::SqliteJournal};
+
+  use super::*;
+
+  #[tokio::test]
+  async fn should_invalidate_day_on_changes() {
+    let journal = CachedJournal::new(
+      SqliteJournal::open(Path::new(":memory:")).unwrap(),
+    );
+    let noon = Local.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap();
+    let later = noon + Duration::hours(1);
+    let date = noon.date_naive();
+    let times = |recs: DayRecords| -> Vec<_> {
+      recs.into_iter().map(|rec| rec.time).collect()
+    };
+    journal.add(Record::new(noon)).await.unwrap();
+    let recs = journal.day_records(date).await.unwrap();
+    assert_eq!(vec![noon], times(recs));
+    journal.add(Record::new(later)).await.unwrap();
+    let recs = journal.day_records(date).await.unwrap();
+    assert_eq!(vec![noon, later], times(recs));
+    journal.remove(noon).await.unwrap();
+    let recs = journal.day_records(date).await.unwrap();
+    assert_eq!(vec![later], times(recs));
+  }
+
+  #[tokio::test]
+  async fn should_keep_records_in_their_day_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let noon = Local.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap();
+    let next_day = noon + Duration::days(1);
+    let (date, next_date) =
+      (noon.date_naive(), next_day.date_naive());
+    let lines =
+      format!("{}\n{}\n", noon.to_rfc3339(), next_day.to_rfc3339());
+    std::fs::write(dir.path().join(format!("{date}.txt")), lines)
+      .unwrap();
+    let journal = FsJournal::new(dir.path());
+    let cached = CachedJournal::new(FsJournal::new(dir.path()));
+    for date in [next_date, date] {
+      assert_eq!(
+        journal.day_records(date).await.unwrap(),
+        cached.day_records(date).await.unwrap()
+      );
+    }
+    assert_eq!(
+      journal.records_between(date, next_date).await.unwrap(),
+      cached.records_between(date, next_date).await.unwrap()
+    );
+  }
+
+  #[tokio::test]
+  async fn should_see_writes_of_other_sqlite_connections() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("journal.sqlite");
+    let journal =
+      CachedJournal::new(SqliteJournal::open(&path).unwrap());
+    let other = SqliteJournal::open(&path).unwrap();
+    let noon = Local.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap();
+    let date = noon.date_naive();
+    assert!(journal.day_records(date).await.unwrap().is_empty());
+    other.add(Record::new(noon)).await.unwrap();
+    let recs = journal.day_records(date).await.unwrap();
+    assert_eq!(1, recs.len());
+  }
+}
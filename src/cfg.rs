@@ -0,0 +1,496 @@
+use std::{
+  collections::HashSet,
+  env, fs, io,
+  path::{Path, PathBuf},
+};
+
+use anyhow::{bail, Context, Result};
+use ratatui::style::Color;
+use serde::{Deserialize, Serialize};
+use toml_edit::{ArrayOfTables, DocumentMut, Item, Table};
+
+use crate::{
+  app::journal::Journal,
+  cached_journal::CachedJournal,
+  fs_journal::FsJournal,
+  keymap::{Keymap, Keys},
+  sqlite_journal::SqliteJournal,
+  theme::Theme,
+};
+
+const HEADER: &str = "\
+# Journal configuration.
+#
+# Every [[tabs]] entry is a tracker shown as a tab:
+#   title  - tab title
+#   target - desired records count per day
+#   dir    - storage directory, relative to the journals directory
+#            (optional, lowercased title by default)
+#   color  - tab title and accent colour, a name like \"magenta\"
+#            or \"#rrggbb\" (optional)
+#   backend - \"fs\" for a text file per day or \"sqlite\" for a
+#            database file in the storage directory (optional, \"fs\"
+#            by default)
+#
+# Set `cache = false` to read journals from storage on every change
+# of the displayed date.
+#
+# Set `days = N` to show at most N days in the Days chart, as many
+# as fit the terminal by default.
+#
+# Key bindings are set in the [keys] section:
+#   preset - \"default\" or \"vim\" for h/j/k/l navigation (optional)
+#   bind   - key chords like \"ctrl+z\" or \"U\" mapped to commands
+#            like \"undo\", \"tab-2\" or \"none\" to unbind (optional)
+#
+# Colours are set in the [theme] section, NO_COLOR disables them:
+#   palette - \"dark\", \"light\" or \"high-contrast\" (optional)
+#   colors  - \"true\", \"256\", \"16\" or \"none\" colours of the
+#            terminal (optional, guessed from COLORTERM and TERM)
+
+";
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+  #[serde(default = "enabled")]
+  pub cache: bool,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub days: Option<u64>,
+  pub tabs: Vec<Tab>,
+  #[serde(default, skip_serializing_if = "Keys::is_default")]
+  pub keys: Keys,
+  #[serde(default, skip_serializing_if = "Theme::is_default")]
+  pub theme: Theme,
+  #[serde(skip, default = "default_journals_dir")]
+  pub journals_dir: PathBuf,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Tab {
+  pub title: String,
+  pub target: usize,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub dir: Option<PathBuf>,
+  #[serde(
+    default,
+    skip_serializing_if = "Option::is_none",
+    with = "color"
+  )]
+  pub color: Option<Color>,
+  #[serde(default, skip_serializing_if = "Backend::is_default")]
+  pub backend: Backend,
+}
+
+#[derive(Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+  #[default]
+  Fs,
+  Sqlite,
+}
+
+impl Backend {
+  fn is_default(&self) -> bool {
+    self == &Self::default()
+  }
+}
+
+impl Default for Config {
+  fn default() -> Self {
+    Self {
+      cache: true,
+      days: None,
+      tabs: vec![Tab::new("Smokes", 1), Tab::new("Trains", 4)],
+      keys: Keys::default(),
+      theme: Theme::default(),
+      journals_dir: default_journals_dir(),
+    }
+  }
+}
+
+impl Config {
+  /// Loads config from [`path`], generating the default one on first
+  /// run
+  pub fn load() -> Result<Self> {
+    Self::load_from(&path())
+  }
+
+  pub fn load_from(path: &Path) -> Result<Self> {
+    if !path.try_exists()? {
+      let cfg = Self::default();
+      cfg.save_to(path)?;
+      return Ok(cfg);
+    }
+    let text = fs::read_to_string(path)
+      .with_context(|| format!("can't read {}", path.display()))?;
+    let cfg: Self = toml::from_str(&text).with_context(|| {
+      format!("invalid config {}", path.display())
+    })?;
+    cfg.validate().with_context(|| {
+      format!("invalid config {}", path.display())
+    })?;
+    Ok(cfg)
+  }
+
+  pub fn with_journals_dir(mut self, dir: PathBuf) -> Self {
+    self.journals_dir = dir;
+    self
+  }
+
+  /// Storage directory of the tab
+  pub fn dir(&self, tab: &Tab) -> PathBuf {
+    let dir = tab
+      .dir
+      .clone()
+      .unwrap_or_else(|| tab.title.to_lowercase().into());
+    self.journals_dir.join(dir)
+  }
+
+  pub fn journal(&self, tab: &Tab) -> Result<Box<dyn Journal>> {
+    let journal: Box<dyn Journal> = match tab.backend {
+      Backend::Fs => Box::new(FsJournal::new(self.dir(tab))),
+      Backend::Sqlite => {
+        Box::new(SqliteJournal::open(&self.sqlite_path(tab))?)
+      }
+    };
+    Ok(if self.cache {
+      Box::new(CachedJournal::new(journal))
+    } else {
+      journal
+    })
+  }
+
+  pub fn sqlite_path(&self, tab: &Tab) -> PathBuf {
+    self.dir(tab).join("journal.sqlite3")
+  }
+
+  pub fn history_path(&self, tab: &Tab) -> PathBuf {
+    self.dir(tab).join("history.toml")
+  }
+
+  /// Saves config to [`path`]
+  pub fn save(&self) -> Result<()> {
+    self.save_to(&path())
+  }
+
+  /// Updates the file at `path` in place, keeping comments and
+  /// formatting of the settings that remain
+  pub fn save_to(&self, path: &Path) -> Result<()> {
+    if let Some(dir) = path.parent() {
+      fs::create_dir_all(dir)?;
+    }
+    let new = toml::to_string_pretty(self)?;
+    let text = match fs::read_to_string(path) {
+      Ok(old) => {
+        let mut doc: DocumentMut =
+          old.parse().with_context(|| {
+            format!("invalid config {}", path.display())
+          })?;
+        let new: DocumentMut = new.parse()?;
+        merge_tables(doc.as_table_mut(), new.as_table());
+        place_tables(doc.as_table_mut(), &mut 1);
+        doc.to_string()
+      }
+      Err(err) if err.kind() == io::ErrorKind::NotFound => {
+        HEADER.to_owned() + &new
+      }
+      Err(err) => {
+        return Err(err)
+          .with_context(|| format!("can't read {}", path.display()))
+      }
+    };
+    fs::write(path, text)
+      .with_context(|| format!("can't write {}", path.display()))?;
+    Ok(())
+  }
+
+  /// Finds tab by title or directory name, case insensitive
+  pub fn tab(&self, name: &str) -> Result<&Tab> {
+    let name = name.to_lowercase();
+    self
+      .tabs
+      .iter()
+      .find(|tab| {
+        tab.title.to_lowercase() == name
+          || self
+            .dir(tab)
+            .file_name()
+            .is_some_and(|dir| dir == &*name)
+      })
+      .with_context(|| {
+        let titles: Vec<_> =
+          self.tabs.iter().map(|tab| tab.title.as_str()).collect();
+        format!(
+          "unknown tab `{name}`, expected one of: {}",
+          titles.join(", ")
+        )
+      })
+  }
+
+  pub fn validate(&self) -> Result<()> {
+    if self.tabs.is_empty() {
+      bail!("at least one tab expected");
+    }
+    if self.days.is_some_and(|days| days < 2) {
+      bail!("at least 2 days expected");
+    }
+    let mut titles = HashSet::new();
+    let mut dirs = HashSet::new();
+    for tab in &self.tabs {
+      if tab.title.trim().is_empty() {
+        bail!("tab title can't be empty");
+      }
+      if !titles.insert(tab.title.to_lowercase()) {
+        bail!("duplicated tab `{}`", tab.title);
+      }
+      if !dirs.insert(self.dir(tab)) {
+        bail!("tab `{}` shares directory with another", tab.title);
+      }
+    }
+    Keymap::new(&self.keys).context("invalid [keys]")?;
+    Ok(())
+  }
+}
+
+impl Tab {
+  pub fn new(title: impl Into<String>, target: usize) -> Self {
+    Self {
+      title: title.into(),
+      target,
+      dir: None,
+      color: None,
+      backend: Backend::default(),
+    }
+  }
+}
+
+pub fn path() -> PathBuf {
+  env::var_os("XDG_CONFIG_HOME")
+    .map_or_else(|| home().join(".config"), PathBuf::from)
+    .join("journal")
+    .join("config.toml")
+}
+
+/// Resolves journals directory with precedence: `flag`,
+/// `JOURNAL_DIR` env var, XDG data dir, `$HOME/.journals`
+pub fn journals_dir(flag: Option<PathBuf>) -> PathBuf {
+  flag
+    .or_else(|| env::var_os("JOURNAL_DIR").map(PathBuf::from))
+    .or_else(|| {
+      env::var_os("XDG_DATA_HOME")
+        .map(|dir| PathBuf::from(dir).join("journal"))
+    })
+    .unwrap_or_else(|| home().join(".journals"))
+}
+
+fn enabled() -> bool {
+  true
+}
+
+fn default_journals_dir() -> PathBuf {
+  journals_dir(None)
+}
+
+fn home() -> PathBuf {
+  env::var_os("HOME").map(PathBuf::from).unwrap_or_default()
+}
+
+/// Updates `old` to the entries of `new`, keeping decor of the
+/// entries that remain
+fn merge_tables(old: &mut Table, new: &Table) {
+  let removed: Vec<_> = old
+    .iter()
+    .map(|(key, _)| key.to_owned())
+    .filter(|key| !new.contains_key(key))
+    .collect();
+  for key in removed {
+    old.remove(&key);
+  }
+  for (key, item) in new.iter() {
+    match old.get_mut(key) {
+      Some(old) => merge_items(old, item),
+      None => {
+        old.insert(key, item.clone());
+      }
+    }
+  }
+}
+
+/// Numbers nested tables in the order of their keys, as tables are
+/// written by position
+fn place_tables(table: &mut Table, position: &mut isize) {
+  for (_, item) in table.iter_mut() {
+    let tables: Vec<_> = match item {
+      Item::Table(table) => vec![table],
+      Item::ArrayOfTables(array) => array.iter_mut().collect(),
+      _ => vec![],
+    };
+    for table in tables {
+      table.set_position(Some(*position));
+      *position += 1;
+      place_tables(table, position);
+    }
+  }
+}
+
+fn merge_items(old: &mut Item, new: &Item) {
+  match (old, new) {
+    (Item::Table(old), Item::Table(new)) => merge_tables(old, new),
+    (Item::ArrayOfTables(old), Item::ArrayOfTables(new)) => {
+      let title = |table: &Table| {
+        table.get("title").and_then(Item::as_str).map(str::to_owned)
+      };
+      let mut merged = ArrayOfTables::new();
+      for (i, new) in new.iter().enumerate() {
+        // Tabs are matched by title to follow moves, by position to
+        // follow renames
+        let mut table = old
+          .iter()
+          .find(|old| {
+            title(old).is_some() && title(old) == title(new)
+          })
+          .or_else(|| old.get(i))
+          .cloned()
+          .unwrap_or_default();
+        merge_tables(&mut table, new);
+        merged.push(table);
+      }
+      *old = merged;
+    }
+    (Item::Value(old), Item::Value(new)) => {
+      let decor = old.decor().clone();
+      *old = new.clone();
+      *old.decor_mut() = decor;
+    }
+    (old, new) => *old = new.clone(),
+  }
+}
+
+mod color {
+  use ratatui::style::Color;
+  use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+  pub fn serialize<S: Serializer>(
+    color: &Option<Color>,
+    s: S,
+  ) -> Result<S::Ok, S::Error> {
+    match color {
+      Some(color) => s.serialize_str(&color.to_string()),
+      None => s.serialize_none(),
+    }
+  }
+
+  pub fn deserialize<'de, D: Deserializer<'de>>(
+    d: D,
+  ) -> Result<Option<Color>, D::Error> {
+    Option::<String>::deserialize(d)?
+      .map(|name| {
+        name.parse().map_err(|_| {
+          D::Error::custom(format!("unknown colour `{name}`"))
+        })
+      })
+      .transpose()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn should_parse_tabs() {
+    let cfg: Config = toml::from_str(
+      r##"
+        [[tabs]]
+        title = "Coffee"
+        target = 2
+        dir = "drinks/coffee"
+        color = "#ff8800"
+      "##,
+    )
+    .unwrap();
+    let cfg = cfg.with_journals_dir("/journals".into());
+    let tab = &cfg.tabs[0];
+    assert_eq!("Coffee", tab.title);
+    assert_eq!(2, tab.target);
+    assert_eq!(Some(Color::Rgb(0xff, 0x88, 0)), tab.color);
+    assert_eq!(
+      PathBuf::from("/journals/drinks/coffee"),
+      cfg.dir(tab)
+    );
+  }
+
+  #[test]
+  fn should_reject_unknown_colour() {
+    let res = toml::from_str::<Config>(
+      r#"
+        [[tabs]]
+        title = "Coffee"
+        target = 2
+        color = "brownish"
+      "#,
+    );
+    assert!(res.is_err());
+  }
+
+  #[test]
+  fn should_reject_shared_dir() {
+    let mut cfg = Config::default();
+    let mut tab = Tab::new("Coffee", 2);
+    tab.dir = Some("smokes".into());
+    cfg.tabs.push(tab);
+    assert!(cfg.validate().is_err());
+  }
+
+  #[test]
+  fn should_keep_comments_on_save() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("config.toml");
+    let text = r#"# Mine
+cache = false # slow disk
+
+# Drinks
+[[tabs]]
+title = "Coffee"
+target = 2 # cups
+
+[[tabs]]
+title = "Tea"
+target = 1
+
+[keys]
+preset = "vim"
+"#;
+    fs::write(&path, text).unwrap();
+    let mut cfg = Config::load_from(&path).unwrap();
+    cfg.tabs.swap(0, 1);
+    cfg.tabs[1].target = 3;
+    cfg.tabs.push(Tab::new("Water", 8));
+    cfg.days = Some(7);
+    cfg.save_to(&path).unwrap();
+    let saved = fs::read_to_string(&path).unwrap();
+    let expected = r#"# Mine
+cache = false # slow disk
+days = 7
+
+[[tabs]]
+title = "Tea"
+target = 1
+
+# Drinks
+[[tabs]]
+title = "Coffee"
+target = 3 # cups
+
+[[tabs]]
+title = "Water"
+target = 8
+
+[keys]
+preset = "vim"
+"#;
+    assert_eq!(expected, saved);
+  }
+}
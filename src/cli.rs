@@ -0,0 +1,323 @@
+use std::path::PathBuf;
+
+use anyhow::{ensure, Context, Result};
+use chrono::{DateTime, Local, NaiveDate, NaiveTime, Timelike};
+use clap::{CommandFactory, FromArgMatches, Parser, Subcommand};
+
+use crate::{
+  app::{
+    journal::Journal,
+    record::{parse_time, unique_time, Record},
+    tab::Tab,
+  },
+  cfg::{self, Backend, Config},
+  fs_journal::FsJournal,
+  sqlite_journal::SqliteJournal,
+};
+
+/// Journal of daily habits. Launches the TUI when no command given
+#[derive(Parser)]
+#[command(version)]
+pub struct Cli {
+  /// Journals directory, overrides `JOURNAL_DIR` env var
+  #[arg(long, global = true)]
+  pub dir: Option<PathBuf>,
+  #[command(subcommand)]
+  pub command: Option<Command>,
+}
+
+impl Cli {
+  /// Parses args, showing resolved journals directory in help
+  pub fn parse_args() -> Self {
+    // Pre-parsed for `--dir` only, as help exits while parsing
+    let dir = Self::command()
+      .ignore_errors(true)
+      .disable_help_flag(true)
+      .disable_version_flag(true)
+      .try_get_matches()
+      .ok()
+      .and_then(|matches| matches.get_one::<PathBuf>("dir").cloned());
+    let journals_dir = cfg::journals_dir(dir);
+    let matches = Self::command()
+      .after_help(format!("Journals: {}", journals_dir.display()))
+      .get_matches();
+    Self::from_arg_matches(&matches).unwrap_or_else(|err| err.exit())
+  }
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+  /// Add a record, now by default
+  Add {
+    /// Tab title or directory name
+    tab: String,
+    /// Record time as `HH:MM`, current time by default
+    #[arg(long)]
+    at: Option<String>,
+    /// Day of the record, today by default
+    #[arg(long)]
+    date: Option<NaiveDate>,
+    /// Quantity of the record, counted as 1 by default
+    #[arg(short, long)]
+    quantity: Option<u32>,
+    /// Tag of the record, may be repeated
+    #[arg(short, long = "tag")]
+    tags: Vec<String>,
+    /// Free text note
+    #[arg(short, long)]
+    note: Option<String>,
+  },
+  /// List records of the day
+  List {
+    /// Tab title or directory name
+    tab: String,
+    /// Day to list, today by default
+    #[arg(long)]
+    date: Option<NaiveDate>,
+  },
+  /// Remove a record, the last one of the day by default
+  Remove {
+    /// Tab title or directory name
+    tab: String,
+    /// Record time as `HH:MM` or RFC 3339 timestamp
+    time: Option<String>,
+    /// Day of the record, today by default
+    #[arg(long)]
+    date: Option<NaiveDate>,
+  },
+  /// Show statistics of all tabs or a single one
+  Stats {
+    /// Tab title or directory name
+    tab: Option<String>,
+  },
+  /// Check day files of all tabs or a single one for malformed
+  /// lines, duplicates, unsorted records and misplaced ones, failing
+  /// when problems are left
+  Fsck {
+    /// Tab title or directory name
+    tab: Option<String>,
+    /// Fix found problems, backing up changed files
+    #[arg(long)]
+    fix: bool,
+  },
+  /// Import day files of the tab into its SQLite database
+  Migrate {
+    /// Tab title or directory name
+    tab: String,
+    /// Directory with day files, the tab directory by default
+    #[arg(long)]
+    from: Option<PathBuf>,
+  },
+}
+
+pub async fn run(cmd: Command, cfg: &Config) -> Result<()> {
+  match cmd {
+    Command::Add {
+      tab,
+      at,
+      date,
+      quantity,
+      tags,
+      note,
+    } => {
+      let now = Local::now();
+      let time = match (at, date) {
+        (None, None) => now,
+        (at, date) => parse_time(
+          date.unwrap_or_else(today),
+          &at.unwrap_or_else(|| now.format("%R").to_string()),
+        )?,
+      };
+      let rec = Record {
+        quantity,
+        tags,
+        note,
+        ..Record::new(time)
+      };
+      rec.validate()?;
+      add(cfg, cfg.tab(&tab)?, rec).await
+    }
+    Command::List { tab, date } => {
+      list(cfg, cfg.tab(&tab)?, date.unwrap_or_else(today)).await
+    }
+    Command::Remove { tab, time, date } => {
+      let date = date.unwrap_or_else(today);
+      remove(cfg, cfg.tab(&tab)?, time.as_deref(), date).await
+    }
+    Command::Stats { tab: Some(tab) } => {
+      stats(cfg, cfg.tab(&tab)?).await
+    }
+    Command::Stats { tab: None } => {
+      for tab in &cfg.tabs {
+        stats(cfg, tab).await?;
+      }
+      Ok(())
+    }
+    Command::Fsck { tab, fix } => {
+      let tabs = match &tab {
+        Some(tab) => vec![cfg.tab(tab)?],
+        None => cfg.tabs.iter().collect(),
+      };
+      let mut problems = 0;
+      for tab in tabs {
+        problems += fsck(cfg, tab, fix).await?;
+      }
+      ensure!(
+        problems == 0,
+        "{problems} problems found, run with `--fix` to fix them"
+      );
+      Ok(())
+    }
+    Command::Migrate { tab, from } => {
+      migrate(cfg, cfg.tab(&tab)?, from).await
+    }
+  }
+}
+
+async fn add(
+  cfg: &Config,
+  tab: &cfg::Tab,
+  rec: Record,
+) -> Result<()> {
+  let journal = cfg.journal(tab)?;
+  let recs = journal.day_records(rec.time.date_naive()).await?;
+  let time = unique_time(&recs, rec.time);
+  journal.add(Record { time, ..rec }).await?;
+  println!("{}: added {}", tab.title, time.format("%R"));
+  Ok(())
+}
+
+async fn list(
+  cfg: &Config,
+  tab: &cfg::Tab,
+  date: NaiveDate,
+) -> Result<()> {
+  let recs = cfg.journal(tab)?.day_records(date).await?;
+  for (i, rec) in recs.iter().enumerate() {
+    let line = format!(
+      "{}) {} {}",
+      i + 1,
+      rec.time.format("%R"),
+      rec.details()
+    );
+    println!("{}", line.trim_end());
+  }
+  Ok(())
+}
+
+async fn remove(
+  cfg: &Config,
+  tab: &cfg::Tab,
+  time: Option<&str>,
+  date: NaiveDate,
+) -> Result<()> {
+  let journal = cfg.journal(tab)?;
+  let exact = time.and_then(|t| DateTime::parse_from_rfc3339(t).ok());
+  let date = exact.map_or(date, |dt| dt.date_naive());
+  let recs = journal.day_records(date).await?;
+  let mut recs = recs.into_iter().map(|rec| rec.time);
+  let dt = match (time, exact) {
+    (None, _) => recs.next_back(),
+    (Some(_), Some(exact)) => recs.find(|dt| dt == &exact),
+    (Some(time), None) => {
+      let time =
+        NaiveTime::parse_from_str(time, "%R").with_context(|| {
+          format!(
+            "invalid time `{time}`, expected `HH:MM` or RFC 3339"
+          )
+        })?;
+      recs.rev().find(|dt| {
+        (dt.hour(), dt.minute()) == (time.hour(), time.minute())
+      })
+    }
+  };
+  let dt = dt.with_context(|| {
+    format!("{}: no such record on {date}", tab.title)
+  })?;
+  journal.remove(dt).await?;
+  println!("{}: removed {}", tab.title, dt.format("%R"));
+  Ok(())
+}
+
+async fn stats(cfg: &Config, tab_cfg: &cfg::Tab) -> Result<()> {
+  let mut tab =
+    Tab::new(&tab_cfg.title, tab_cfg.target, cfg.journal(tab_cfg)?);
+  tab.resolve_all().await?;
+  let state = tab.state();
+  let week: usize = state
+    .recs_by_date
+    .iter()
+    .rev()
+    .take(7)
+    .map(|(_, n)| n)
+    .sum();
+  let year: usize = state.recs_by_month.values().sum();
+  println!(
+    "{}: today {}/{} ({:.0}%), last 7 days {week}, last year {year}",
+    tab_cfg.title,
+    state.level.count(),
+    state.level.target(),
+    state.level.percentage() * 100.,
+  );
+  Ok(())
+}
+
+/// Checks day files of the tab, returning the count of problems left
+async fn fsck(
+  cfg: &Config,
+  tab: &cfg::Tab,
+  fix: bool,
+) -> Result<usize> {
+  if tab.backend != Backend::Fs {
+    println!("{}: skipped, not stored in day files", tab.title);
+    return Ok(0);
+  }
+  let journal = FsJournal::new(cfg.dir(tab));
+  let problems = journal.check().await?;
+  for problem in &problems {
+    println!("{problem}");
+  }
+  match (problems.len(), fix) {
+    (0, _) => println!("{}: ok", tab.title),
+    (count, false) => {
+      println!("{}: {count} problems", tab.title);
+      return Ok(count);
+    }
+    (_, true) => {
+      let changed = journal.repair().await?;
+      println!(
+        "{}: fixed {changed} files, originals kept as *.bak",
+        tab.title
+      );
+    }
+  }
+  Ok(0)
+}
+
+async fn migrate(
+  cfg: &Config,
+  tab: &cfg::Tab,
+  from: Option<PathBuf>,
+) -> Result<()> {
+  let from = FsJournal::new(from.unwrap_or_else(|| cfg.dir(tab)));
+  let mut recs = vec![];
+  for date in from.dates().await? {
+    recs.extend(from.day_records(date).await?);
+  }
+  let total = recs.len();
+  let path = cfg.sqlite_path(tab);
+  let imported = SqliteJournal::open(&path)?.import(recs).await?;
+  println!(
+    "{}: imported {imported} of {total} records into {}",
+    tab.title,
+    path.display()
+  );
+  if tab.backend != Backend::Sqlite {
+    println!("set `backend = \"sqlite\"` for the tab to use it");
+  }
+  Ok(())
+}
+
+fn today() -> NaiveDate {
+  Local::now().date_naive()
+}
@@ -1,70 +1,436 @@
 use std::{
-  io,
+  collections::{BTreeMap, HashMap, HashSet},
+  fmt, fs, io,
   path::{Path, PathBuf},
+  sync::{Arc, Mutex, OnceLock},
+  time::SystemTime,
 };
 
-use anyhow::{Context, Result};
+use anyhow::Result;
 use async_trait::async_trait;
 use chrono::{DateTime, Local, NaiveDate};
-use tokio::fs::{create_dir_all, read_to_string, try_exists, write};
+use futures::future::try_join_all;
+use notify::{
+  Event, RecommendedWatcher, RecursiveMode, Watcher as _,
+};
+use tokio::{
+  fs::{
+    copy, create_dir_all, metadata, read_dir, read_to_string,
+    remove_file, rename, try_exists, DirEntry, File,
+  },
+  io::AsyncWriteExt,
+  sync::watch,
+  task::spawn_blocking,
+};
+
+use crate::app::{
+  journal::{days, DayRecords, Journal, Stamp},
+  record::Record,
+};
+
+/// Day file split into records and lines failed to parse
+#[derive(Default)]
+struct DayFile {
+  recs: DayRecords,
+  /// Malformed lines with their 1-based numbers and parse errors
+  malformed: Vec<(usize, String, String)>,
+}
 
-use crate::app::journal::{DayRecords, Journal};
+impl DayFile {
+  fn parse(text: &str) -> Self {
+    let mut day = Self::default();
+    for (i, line) in text.lines().enumerate() {
+      if line.trim().is_empty() {
+        continue;
+      }
+      match line.parse() {
+        Ok(rec) => day.recs.push(rec),
+        Err(err) => {
+          day.malformed.push((
+            i + 1,
+            line.into(),
+            format!("{err:#}"),
+          ));
+        }
+      }
+    }
+    day
+  }
+}
+
+/// Problem of a day file found by [`FsJournal::check`]
+pub enum Problem {
+  Malformed {
+    path: PathBuf,
+    line: usize,
+    text: String,
+    err: String,
+  },
+  Duplicate {
+    path: PathBuf,
+    time: DateTime<Local>,
+  },
+  Unsorted {
+    path: PathBuf,
+  },
+  Misplaced {
+    path: PathBuf,
+    time: DateTime<Local>,
+  },
+}
+
+impl fmt::Display for Problem {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Problem::Malformed {
+        path,
+        line,
+        text,
+        err,
+      } => write!(
+        f,
+        "{}:{line}: malformed line `{text}`: {err}",
+        path.display()
+      ),
+      Problem::Duplicate { path, time } => write!(
+        f,
+        "{}: duplicate record at {}",
+        path.display(),
+        time.to_rfc3339()
+      ),
+      Problem::Unsorted { path } => {
+        write!(f, "{}: records are out of order", path.display())
+      }
+      Problem::Misplaced { path, time } => write!(
+        f,
+        "{}: record at {} belongs to another day",
+        path.display(),
+        time.to_rfc3339()
+      ),
+    }
+  }
+}
 
 pub struct FsJournal {
   dir: PathBuf,
+  watcher: OnceLock<(RecommendedWatcher, watch::Receiver<()>)>,
+  /// Modification times of day files after own writes, so the
+  /// watcher skips them
+  written: Arc<Mutex<HashMap<PathBuf, SystemTime>>>,
 }
 
 impl FsJournal {
   pub fn new(dir: impl Into<PathBuf>) -> Self {
-    Self { dir: dir.into() }
+    Self {
+      dir: dir.into(),
+      watcher: OnceLock::new(),
+      written: Arc::default(),
+    }
   }
 
   fn path(&self, date: NaiveDate) -> PathBuf {
     self.dir.join(format!("{date}.txt"))
   }
+
+  /// Takes advisory lock of the journal directory, shared with other
+  /// processes. Released when the returned file dropped
+  async fn lock(&self) -> Result<fs::File> {
+    create_dir_all(&self.dir).await?;
+    let path = self.dir.join(".lock");
+    spawn_blocking(move || {
+      let file = fs::File::options()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&path)?;
+      file.lock()?;
+      Ok(file)
+    })
+    .await?
+  }
+
+  async fn read(&self, date: NaiveDate) -> Result<DayFile> {
+    let text = read_if_exist(&self.path(date)).await?;
+    Ok(DayFile::parse(&text.unwrap_or_default()))
+  }
+
+  /// Replaces day file with a renamed temporary one, so readers and
+  /// crashes never see it partially written. Malformed lines are kept
+  /// as is for [`FsJournal::repair`]
+  async fn write(&self, date: NaiveDate, day: DayFile) -> Result<()> {
+    let tmp_path = self.dir.join(format!(".{date}.txt.tmp"));
+    let mut tmp = File::create(&tmp_path).await?;
+    let recs = day.recs.iter().map(ToString::to_string);
+    let malformed =
+      day.malformed.into_iter().map(|(_, line, _)| line);
+    let text: String =
+      recs.chain(malformed).map(|line| line + "\n").collect();
+    tmp.write_all(text.as_bytes()).await?;
+    tmp.sync_all().await?;
+    // Renaming keeps the time, noted first to beat the watcher
+    let modified = tmp.metadata().await?.modified()?;
+    let path = self.path(date);
+    self
+      .written
+      .lock()
+      .expect("poisoned writes")
+      .insert(path.clone(), modified);
+    rename(&tmp_path, &path).await?;
+    Ok(())
+  }
+
+  /// Dates having day file, unordered
+  pub async fn dates(&self) -> Result<Vec<NaiveDate>> {
+    Ok(
+      self
+        .day_files()
+        .await?
+        .into_iter()
+        .map(|(date, _)| date)
+        .collect(),
+    )
+  }
+
+  /// Finds malformed lines, duplicates, unsorted files and records
+  /// stored in a file of another day
+  pub async fn check(&self) -> Result<Vec<Problem>> {
+    let mut problems = vec![];
+    let mut dates = self.dates().await?;
+    dates.sort_unstable();
+    for date in dates {
+      let path = self.path(date);
+      let day = self.read(date).await?;
+      problems.extend(day.malformed.into_iter().map(
+        |(line, text, err)| Problem::Malformed {
+          path: path.clone(),
+          line,
+          text,
+          err,
+        },
+      ));
+      let mut times = HashSet::new();
+      for rec in &day.recs {
+        if !times.insert(rec.time) {
+          let time = rec.time;
+          problems.push(Problem::Duplicate {
+            path: path.clone(),
+            time,
+          });
+        }
+        if rec.time.date_naive() != date {
+          let time = rec.time;
+          problems.push(Problem::Misplaced {
+            path: path.clone(),
+            time,
+          });
+        }
+      }
+      if day.recs.windows(2).any(|recs| recs[0].time > recs[1].time) {
+        problems.push(Problem::Unsorted { path });
+      }
+    }
+    Ok(problems)
+  }
+
+  /// Fixes problems found by [`FsJournal::check`]: drops malformed
+  /// lines and duplicates, sorts records and moves them to files of
+  /// their days. Changed files are backed up next to them first.
+  /// Returns count of changed files
+  pub async fn repair(&self) -> Result<usize> {
+    let _lock = self.lock().await?;
+    let suffix = Local::now().format("%Y%m%d%H%M%S");
+    let mut days = BTreeMap::<_, DayRecords>::new();
+    let mut old = BTreeMap::new();
+    for date in self.dates().await? {
+      let day = self.read(date).await?;
+      for rec in &day.recs {
+        let recs = days.entry(rec.time.date_naive()).or_default();
+        if recs.iter().all(|r| r.time != rec.time) {
+          recs.push(rec.clone());
+        }
+      }
+      old.insert(date, day);
+    }
+    let mut changed = 0;
+    let dates: HashSet<_> = days.keys().chain(old.keys()).collect();
+    for &date in dates {
+      let mut recs = days.get(&date).cloned().unwrap_or_default();
+      recs.sort_unstable();
+      let old = old.get(&date);
+      if old.is_some_and(|old| {
+        old.malformed.is_empty() && old.recs == recs
+      }) {
+        continue;
+      }
+      let path = self.path(date);
+      if old.is_some() {
+        let backup = format!("{date}.txt.{suffix}.bak");
+        copy(&path, self.dir.join(backup)).await?;
+      }
+      if recs.is_empty() {
+        remove_file(&path).await?;
+      } else {
+        let day = DayFile {
+          recs,
+          ..DayFile::default()
+        };
+        self.write(date, day).await?;
+      }
+      changed += 1;
+    }
+    Ok(changed)
+  }
+
+  async fn day_files(&self) -> Result<Vec<(NaiveDate, DirEntry)>> {
+    let mut files = vec![];
+    if !try_exists(&self.dir).await? {
+      return Ok(files);
+    }
+    let mut entries = read_dir(&self.dir).await?;
+    while let Some(entry) = entries.next_entry().await? {
+      let name = entry.file_name();
+      let date = name
+        .to_str()
+        .and_then(|name| name.strip_suffix(".txt"))
+        .and_then(|date| date.parse::<NaiveDate>().ok());
+      files.extend(date.map(|date| (date, entry)));
+    }
+    Ok(files)
+  }
 }
 
 #[async_trait]
 impl Journal for FsJournal {
+  /// Records of the day, skipping malformed lines
   async fn day_records(&self, date: NaiveDate) -> Result<DayRecords> {
-    read_if_exist(&self.path(date))
-      .await?
-      .unwrap_or_default()
-      .lines()
-      .map(DateTime::parse_from_rfc3339)
-      .map(|dt| dt.map(|dt| dt.with_timezone(&Local)))
-      .collect::<Result<_, _>>()
-      .context("date parse error")
-  }
-
-  async fn add(&self, dt: DateTime<Local>) -> Result<()> {
-    let mut recs = self.day_records(dt.date_naive()).await?;
-    recs.push(dt);
-    recs.sort_unstable();
-    create_dir_all(&self.dir).await?;
-    write(
-      self.path(dt.date_naive()),
-      recs.into_iter().map(date_time_line).collect::<String>(),
-    )
-    .await?;
-    Ok(())
+    Ok(self.read(date).await?.recs)
+  }
+
+  async fn add(&self, rec: Record) -> Result<()> {
+    let _lock = self.lock().await?;
+    let date = rec.time.date_naive();
+    let mut day = self.read(date).await?;
+    day.recs.push(rec);
+    day.recs.sort_unstable();
+    self.write(date, day).await
   }
 
   async fn remove(&self, dt: DateTime<Local>) -> Result<()> {
-    let recs = self.day_records(dt.date_naive()).await?;
-    if !recs.is_empty() {
-      write(
-        self.path(dt.date_naive()),
-        recs
-          .into_iter()
-          .filter(|rec| rec != &dt)
-          .map(date_time_line)
-          .collect::<String>(),
-      )
-      .await?;
+    let _lock = self.lock().await?;
+    let mut day = self.read(dt.date_naive()).await?;
+    if !day.recs.is_empty() {
+      day.recs.retain(|rec| rec.time != dt);
+      self.write(dt.date_naive(), day).await?;
+    }
+    Ok(())
+  }
+
+  async fn update(&self, rec: Record) -> Result<()> {
+    let _lock = self.lock().await?;
+    let date = rec.time.date_naive();
+    let mut day = self.read(date).await?;
+    day.recs.retain(|r| r.time != rec.time);
+    day.recs.push(rec);
+    day.recs.sort_unstable();
+    self.write(date, day).await
+  }
+
+  /// Writes the target day before dropping the record from its day
+  async fn move_record(
+    &self,
+    from: DateTime<Local>,
+    to: Record,
+  ) -> Result<()> {
+    let _lock = self.lock().await?;
+    let (from_date, to_date) =
+      (from.date_naive(), to.time.date_naive());
+    let mut day = self.read(to_date).await?;
+    if from_date == to_date {
+      day.recs.retain(|rec| rec.time != from);
+    }
+    day.recs.push(to);
+    day.recs.sort_unstable();
+    self.write(to_date, day).await?;
+    if from_date != to_date {
+      let mut day = self.read(from_date).await?;
+      day.recs.retain(|rec| rec.time != from);
+      self.write(from_date, day).await?;
     }
     Ok(())
   }
+
+  async fn day_with_malformed(
+    &self,
+    date: NaiveDate,
+  ) -> Result<(DayRecords, usize)> {
+    let day = self.read(date).await?;
+    Ok((day.recs, day.malformed.len()))
+  }
+
+  async fn records_between(
+    &self,
+    start: NaiveDate,
+    end: NaiveDate,
+  ) -> Result<DayRecords> {
+    let mut dates = self.dates().await?;
+    dates.retain(|date| (start..=end).contains(date));
+    dates.sort_unstable();
+    let recs = dates.into_iter().map(|date| self.day_records(date));
+    Ok(try_join_all(recs).await?.concat())
+  }
+
+  async fn stamps_between(
+    &self,
+    start: NaiveDate,
+    end: NaiveDate,
+  ) -> Result<Option<HashMap<NaiveDate, Stamp>>> {
+    let stamps = days(start, end).map(|date| async move {
+      let modified = modified_if_exist(&self.path(date)).await?;
+      Ok::<_, io::Error>(modified.map(|m| (date, Stamp::Modified(m))))
+    });
+    let stamps = try_join_all(stamps).await?;
+    Ok(Some(stamps.into_iter().flatten().collect()))
+  }
+
+  fn watch(&self) -> Result<Option<watch::Receiver<()>>> {
+    if let Some((_, rx)) = self.watcher.get() {
+      return Ok(Some(rx.clone()));
+    }
+    fs::create_dir_all(&self.dir)?;
+    let (tx, rx) = watch::channel(());
+    let written = self.written.clone();
+    let mut watcher = notify::recommended_watcher(
+      move |event: notify::Result<Event>| {
+        let written = written.lock().expect("poisoned writes");
+        if event.is_ok_and(|event| is_day_change(&event, &written)) {
+          tx.send_replace(());
+        }
+      },
+    )?;
+    watcher.watch(&self.dir, RecursiveMode::NonRecursive)?;
+    let (_, rx) = self.watcher.get_or_init(|| (watcher, rx));
+    Ok(Some(rx.clone()))
+  }
+}
+
+/// Whether `event` changed a day file other than by the last own
+/// write to it
+fn is_day_change(
+  event: &Event,
+  written: &HashMap<PathBuf, SystemTime>,
+) -> bool {
+  let is_own = |path: &PathBuf| {
+    let modified =
+      fs::metadata(path).and_then(|meta| meta.modified());
+    written
+      .get(path)
+      .is_some_and(|time| modified.ok() == Some(*time))
+  };
+  !event.kind.is_access()
+    && event.paths.iter().any(|path| {
+      path.extension().is_some_and(|ext| ext == "txt")
+        && !is_own(path)
+    })
 }
 
 async fn read_if_exist(path: &Path) -> io::Result<Option<String>> {
@@ -75,6 +441,208 @@ async fn read_if_exist(path: &Path) -> io::Result<Option<String>> {
   }
 }
 
-fn date_time_line(dt: DateTime<Local>) -> String {
-  dt.to_rfc3339() + "\n"
+async fn modified_if_exist(
+  path: &Path,
+) -> io::Result<Option<SystemTime>> {
+  match metadata(path).await {
+    Ok(meta) => meta.modified().map(Some),
+    Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+    Err(err) => Err(err),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::{
+    env,
+    process::{Command, Stdio},
+  };
+
+  use chrono::{Duration, TimeZone};
+  use futures::future::join_all;
+
+  use super::*;
+
+  const CHILD_DIR_VAR: &str = "FS_JOURNAL_TEST_CHILD_DIR";
+  const TASKS: i64 = 8;
+  const ADDS: i64 = 20;
+
+  fn noon() -> DateTime<Local> {
+    Local.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap()
+  }
+
+  /// Adds records from many tasks, every one with own journal
+  async fn hammer(dir: &Path, id: i64) {
+    let tasks = (0..TASKS).map(|task| {
+      let journal = FsJournal::new(dir);
+      tokio::spawn(async move {
+        for i in 0..ADDS {
+          let ms = (id * TASKS + task) * ADDS + i;
+          let time = noon() + Duration::milliseconds(ms);
+          journal.add(Record::new(time)).await?;
+        }
+        anyhow::Ok(())
+      })
+    });
+    for res in join_all(tasks).await {
+      res.unwrap().unwrap();
+    }
+  }
+
+  async fn assert_all_added(dir: &Path, processes: i64) {
+    let recs = FsJournal::new(dir)
+      .day_records(noon().date_naive())
+      .await
+      .unwrap();
+    let expected: Vec<_> = (0..processes * TASKS * ADDS)
+      .map(|ms| Record::new(noon() + Duration::milliseconds(ms)))
+      .collect();
+    assert_eq!(expected, recs);
+  }
+
+  #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+  async fn should_not_lose_records_of_concurrent_tasks() {
+    let dir = tempfile::tempdir().unwrap();
+    hammer(dir.path(), 0).await;
+    assert_all_added(dir.path(), 1).await;
+  }
+
+  #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+  async fn should_not_lose_records_of_concurrent_processes() {
+    const PROCESSES: i64 = 4;
+
+    let dir = tempfile::tempdir().unwrap();
+    let exe = env::current_exe().unwrap();
+    let children: Vec<_> = (0..PROCESSES)
+      .map(|id| {
+        Command::new(&exe)
+          .args(["--exact", "--ignored"])
+          .arg("fs_journal::tests::hammer_in_child_process")
+          .env(CHILD_DIR_VAR, dir.path())
+          .env("FS_JOURNAL_TEST_CHILD_ID", id.to_string())
+          .stdout(Stdio::null())
+          .spawn()
+          .unwrap()
+      })
+      .collect();
+    for mut child in children {
+      assert!(child.wait().unwrap().success());
+    }
+    assert_all_added(dir.path(), PROCESSES).await;
+  }
+
+  #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+  #[ignore = "spawned by should_not_lose_records_of_concurrent_processes"]
+  async fn hammer_in_child_process() {
+    let dir = PathBuf::from(env::var_os(CHILD_DIR_VAR).unwrap());
+    let id = env::var("FS_JOURNAL_TEST_CHILD_ID").unwrap();
+    hammer(&dir, id.parse().unwrap()).await;
+  }
+
+  #[tokio::test]
+  async fn should_not_leave_temp_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let journal = FsJournal::new(dir.path());
+    journal.add(Record::new(noon())).await.unwrap();
+    journal.remove(noon()).await.unwrap();
+    let mut names: Vec<_> = fs::read_dir(dir.path())
+      .unwrap()
+      .map(|entry| entry.unwrap().file_name())
+      .collect();
+    names.sort();
+    assert_eq!(vec![".lock", "2024-01-01.txt"], names);
+  }
+
+  #[tokio::test]
+  async fn should_skip_own_writes_in_watcher() {
+    let dir = tempfile::tempdir().unwrap();
+    let journal = FsJournal::new(dir.path());
+    journal.add(Record::new(noon())).await.unwrap();
+    let path = journal.path(noon().date_naive());
+    let event =
+      Event::new(notify::EventKind::Any).add_path(path.clone());
+    let is_change =
+      || is_day_change(&event, &journal.written.lock().unwrap());
+    assert!(!is_change());
+    let other = FsJournal::new(dir.path());
+    other
+      .add(Record::new(noon() + Duration::hours(1)))
+      .await
+      .unwrap();
+    assert!(is_change());
+  }
+
+  #[tokio::test]
+  async fn should_move_records_within_and_across_days() {
+    let dir = tempfile::tempdir().unwrap();
+    let journal = FsJournal::new(dir.path());
+    let rec = Record::new(noon()).with_details("x2").unwrap();
+    journal.add(rec.clone()).await.unwrap();
+    let later = noon() + Duration::hours(1);
+    let next_day = noon() + Duration::days(1);
+    let moved = |time| Record {
+      time,
+      ..rec.clone()
+    };
+    journal.move_record(noon(), moved(later)).await.unwrap();
+    journal.move_record(later, moved(next_day)).await.unwrap();
+    let date = noon().date_naive();
+    assert!(journal.day_records(date).await.unwrap().is_empty());
+    let recs = journal.day_records(next_day.date_naive()).await;
+    assert_eq!(vec![moved(next_day)], recs.unwrap());
+  }
+
+  #[tokio::test]
+  async fn should_skip_malformed_lines_and_repair_them() {
+    let dir = tempfile::tempdir().unwrap();
+    let journal = FsJournal::new(dir.path());
+    let later = noon() + Duration::hours(1);
+    let next_day = noon() + Duration::days(1);
+    let lines = [
+      later.to_rfc3339(),
+      "garbage".into(),
+      noon().to_rfc3339(),
+      noon().to_rfc3339(),
+      next_day.to_rfc3339(),
+    ];
+    fs::write(journal.path(noon().date_naive()), lines.join("\n"))
+      .unwrap();
+
+    let date = noon().date_naive();
+    let (recs, malformed) =
+      journal.day_with_malformed(date).await.unwrap();
+    assert_eq!((4, 1), (recs.len(), malformed));
+    let problems = journal.check().await.unwrap();
+    // Malformed, duplicate, misplaced and unsorted
+    assert_eq!(4, problems.len());
+    journal
+      .add(Record::new(noon() + Duration::hours(2)))
+      .await
+      .unwrap();
+    let (_, malformed) =
+      journal.day_with_malformed(date).await.unwrap();
+    assert_eq!(1, malformed);
+
+    assert_eq!(2, journal.repair().await.unwrap());
+    assert!(journal.check().await.unwrap().is_empty());
+    let times = |recs: DayRecords| -> Vec<_> {
+      recs.into_iter().map(|rec| rec.time).collect()
+    };
+    let recs = journal.day_records(date).await.unwrap();
+    assert_eq!(
+      vec![noon(), later, noon() + Duration::hours(2)],
+      times(recs)
+    );
+    let recs =
+      journal.day_records(next_day.date_naive()).await.unwrap();
+    assert_eq!(vec![next_day], times(recs));
+    let backups = fs::read_dir(dir.path())
+      .unwrap()
+      .filter(|entry| {
+        let name = entry.as_ref().unwrap().file_name();
+        name.to_string_lossy().ends_with(".bak")
+      })
+      .count();
+    assert_eq!(1, backups);
+  }
 }
@@ -0,0 +1,391 @@
+use std::{collections::BTreeMap, fmt, str::FromStr};
+
+use anyhow::{bail, ensure, Context, Result};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::{Deserialize, Serialize};
+
+use crate::app::Command;
+
+/// Bindable commands with their config names and help descriptions
+const COMMANDS: &[(&str, Command, &str)] = &[
+  ("quit", Command::Quit, "quit"),
+  ("add-record", Command::AddRecord, "add record"),
+  ("add-record-at", Command::AddRecordAt, "add at"),
+  ("edit-record", Command::EditSelectedRecord, "edit record"),
+  ("move-record", Command::MoveSelectedRecord, "move record"),
+  (
+    "delete-record",
+    Command::DeleteSelectedRecord,
+    "delete record",
+  ),
+  ("prev-record", Command::PrevSelection, "previous record"),
+  ("next-record", Command::NextSelection, "next record"),
+  ("prev-date", Command::PrevDate, "previous day"),
+  ("next-date", Command::NextDate, "next day"),
+  ("prev-frame", Command::PrevFrame, "previous days"),
+  ("next-frame", Command::NextFrame, "next days"),
+  ("today", Command::Today, "today"),
+  ("period", Command::TogglePeriod, "day/week/month"),
+  ("go-to-date", Command::GoToDate, "go to date"),
+  ("undo", Command::Undo, "undo"),
+  ("redo", Command::Redo, "redo"),
+  ("global-undo", Command::ToggleGlobalUndo, "global undo"),
+  ("history", Command::ToggleHistory, "history"),
+  ("heatmap", Command::ToggleHeatmap, "heatmap"),
+  ("next-tab", Command::NextTab, "next tab"),
+  ("prev-tab", Command::PrevTab, "previous tab"),
+  ("switch-tab", Command::SwitchTab, "find tab"),
+  ("new-tab", Command::NewTab, "new tab"),
+  ("rename-tab", Command::RenameTab, "rename tab"),
+  ("move-tab-left", Command::MoveTabLeft, "move tab left"),
+  ("move-tab-right", Command::MoveTabRight, "move tab right"),
+  ("archive-tab", Command::ArchiveTab, "archive tab"),
+];
+
+/// Prefix of `tab-1`..`tab-9` commands
+const TAB_PREFIX: &str = "tab-";
+
+const DEFAULT: &[(&str, &str)] = &[
+  ("space", "add-record"),
+  ("a", "add-record-at"),
+  ("e", "edit-record"),
+  ("t", "move-record"),
+  ("backspace", "delete-record"),
+  ("up", "prev-record"),
+  ("down", "next-record"),
+  ("left", "prev-date"),
+  ("right", "next-date"),
+  ("pgup", "prev-frame"),
+  ("pgdn", "next-frame"),
+  ("home", "today"),
+  ("T", "today"),
+  ("p", "period"),
+  ("d", "go-to-date"),
+  ("u", "undo"),
+  ("U", "redo"),
+  ("g", "global-undo"),
+  ("h", "history"),
+  ("c", "heatmap"),
+  ("tab", "next-tab"),
+  ("s-tab", "prev-tab"),
+  ("/", "switch-tab"),
+  ("N", "new-tab"),
+  ("R", "rename-tab"),
+  ("<", "move-tab-left"),
+  (">", "move-tab-right"),
+  ("X", "archive-tab"),
+  ("esc", "quit"),
+];
+
+/// Overrides of [`DEFAULT`] in the vim preset
+const VIM: &[(&str, &str)] = &[
+  ("h", "prev-date"),
+  ("j", "next-record"),
+  ("k", "prev-record"),
+  ("l", "next-date"),
+  ("x", "delete-record"),
+  ("ctrl+r", "redo"),
+  ("H", "history"),
+  ("q", "quit"),
+];
+
+/// `[keys]` section of the config
+#[derive(Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Keys {
+  #[serde(default, skip_serializing_if = "Preset::is_default")]
+  pub preset: Preset,
+  /// Key chords to command names, `none` unbinds a key
+  #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+  pub bind: BTreeMap<String, String>,
+}
+
+impl Keys {
+  pub fn is_default(&self) -> bool {
+    self == &Self::default()
+  }
+}
+
+#[derive(Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Preset {
+  #[default]
+  Default,
+  Vim,
+}
+
+impl Preset {
+  fn is_default(&self) -> bool {
+    self == &Self::default()
+  }
+}
+
+/// Key chord, shift is implied by the char case
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Key {
+  code: KeyCode,
+  modifiers: KeyModifiers,
+}
+
+impl From<KeyEvent> for Key {
+  fn from(event: KeyEvent) -> Self {
+    let mut modifiers = event.modifiers;
+    if let KeyCode::Char(_) | KeyCode::BackTab = event.code {
+      modifiers.remove(KeyModifiers::SHIFT);
+    }
+    Self {
+      code: event.code,
+      modifiers,
+    }
+  }
+}
+
+impl FromStr for Key {
+  type Err = anyhow::Error;
+
+  fn from_str(s: &str) -> Result<Self> {
+    let (mods, name) = match s.strip_suffix('+') {
+      Some(mods) if mods.is_empty() || mods.ends_with('+') => {
+        (mods.strip_suffix('+').unwrap_or_default(), "+")
+      }
+      _ => s.rsplit_once('+').unwrap_or(("", s)),
+    };
+    let mut modifiers = KeyModifiers::NONE;
+    for m in mods.split('+').filter(|m| !m.is_empty()) {
+      modifiers |= match m.to_lowercase().as_str() {
+        "ctrl" => KeyModifiers::CONTROL,
+        "alt" => KeyModifiers::ALT,
+        "shift" => KeyModifiers::SHIFT,
+        _ => bail!("unknown modifier `{m}` in `{s}`"),
+      };
+    }
+    let mut chars = name.chars();
+    let code = match (chars.next(), chars.next()) {
+      (Some(c), None) => KeyCode::Char(c),
+      _ => match name.to_lowercase().as_str() {
+        "space" => KeyCode::Char(' '),
+        "enter" | "return" => KeyCode::Enter,
+        "esc" | "escape" => KeyCode::Esc,
+        "tab" => KeyCode::Tab,
+        "s-tab" | "backtab" => KeyCode::BackTab,
+        "backspace" => KeyCode::Backspace,
+        "delete" | "del" => KeyCode::Delete,
+        "insert" => KeyCode::Insert,
+        "up" => KeyCode::Up,
+        "down" => KeyCode::Down,
+        "left" => KeyCode::Left,
+        "right" => KeyCode::Right,
+        "home" => KeyCode::Home,
+        "end" => KeyCode::End,
+        "pgup" | "pageup" => KeyCode::PageUp,
+        "pgdn" | "pagedown" => KeyCode::PageDown,
+        f => KeyCode::F(
+          f.strip_prefix('f')
+            .and_then(|n| n.parse().ok())
+            .with_context(|| format!("unknown key `{s}`"))?,
+        ),
+      },
+    };
+    // Shift is the char case or BackTab, like crossterm reports it
+    let code = match code {
+      KeyCode::Char(c) if modifiers.contains(KeyModifiers::SHIFT) => {
+        KeyCode::Char(c.to_ascii_uppercase())
+      }
+      KeyCode::Tab if modifiers.contains(KeyModifiers::SHIFT) => {
+        KeyCode::BackTab
+      }
+      code => code,
+    };
+    Ok(Self::from(KeyEvent::new(code, modifiers)))
+  }
+}
+
+impl fmt::Display for Key {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    for (modifier, name) in [
+      (KeyModifiers::CONTROL, "ctrl+"),
+      (KeyModifiers::ALT, "alt+"),
+      (KeyModifiers::SHIFT, "shift+"),
+    ] {
+      if self.modifiers.contains(modifier) {
+        f.write_str(name)?;
+      }
+    }
+    match self.code {
+      KeyCode::Char(' ') => f.write_str("SPACE"),
+      KeyCode::Char(c) => write!(f, "{c}"),
+      KeyCode::Enter => f.write_str("ENTER"),
+      KeyCode::Esc => f.write_str("ESC"),
+      KeyCode::Tab => f.write_str("TAB"),
+      KeyCode::BackTab => f.write_str("S-TAB"),
+      KeyCode::Backspace => f.write_str("BACKSPACE"),
+      KeyCode::Delete => f.write_str("DELETE"),
+      KeyCode::Insert => f.write_str("INSERT"),
+      KeyCode::Up => f.write_str("UP"),
+      KeyCode::Down => f.write_str("DOWN"),
+      KeyCode::Left => f.write_str("LEFT"),
+      KeyCode::Right => f.write_str("RIGHT"),
+      KeyCode::Home => f.write_str("HOME"),
+      KeyCode::End => f.write_str("END"),
+      KeyCode::PageUp => f.write_str("PGUP"),
+      KeyCode::PageDown => f.write_str("PGDN"),
+      KeyCode::F(n) => write!(f, "F{n}"),
+      code => write!(f, "{code:?}"),
+    }
+  }
+}
+
+/// Key chords of the journal view mapped to commands
+#[derive(Clone, Default)]
+pub struct Keymap {
+  bindings: Vec<(Key, Command)>,
+}
+
+impl Keymap {
+  pub fn new(keys: &Keys) -> Result<Self> {
+    let mut keymap = Self::default();
+    let preset = match keys.preset {
+      Preset::Default => &[][..],
+      Preset::Vim => VIM,
+    };
+    for &(key, cmd) in DEFAULT.iter().chain(preset) {
+      keymap.bind(key.parse()?, Some(parse_command(cmd)?));
+    }
+    for i in 1..=9 {
+      keymap.bind(
+        i.to_string().parse()?,
+        Some(Command::SelectTab(i - 1)),
+      );
+    }
+
+    let mut chords: Vec<(Key, &str)> = vec![];
+    for (chord, cmd) in &keys.bind {
+      let key: Key = chord.parse()?;
+      if let Some((_, other)) = chords.iter().find(|(k, _)| *k == key)
+      {
+        bail!("keys `{other}` and `{chord}` are the same");
+      }
+      chords.push((key, chord));
+      let cmd = match cmd.as_str() {
+        "none" => None,
+        cmd => Some(parse_command(cmd).with_context(|| {
+          format!("invalid binding of `{chord}`")
+        })?),
+      };
+      keymap.bind(key, cmd);
+    }
+    ensure!(
+      keymap.bindings.iter().any(|(_, cmd)| *cmd == Command::Quit),
+      "no key to quit"
+    );
+    Ok(keymap)
+  }
+
+  /// Replaces the binding of `key`, unbinds it on `None`
+  fn bind(&mut self, key: Key, cmd: Option<Command>) {
+    let bound = self.bindings.iter().position(|(k, _)| *k == key);
+    match (bound, cmd) {
+      (Some(i), Some(cmd)) => self.bindings[i].1 = cmd,
+      (Some(i), None) => {
+        self.bindings.remove(i);
+      }
+      (None, Some(cmd)) => self.bindings.push((key, cmd)),
+      (None, None) => {}
+    }
+  }
+
+  pub fn command(&self, event: KeyEvent) -> Option<Command> {
+    let key = Key::from(event);
+    self
+      .bindings
+      .iter()
+      .find_map(|(k, cmd)| (*k == key).then_some(*cmd))
+  }
+
+  /// `keys - description` entries in [`COMMANDS`] order
+  pub fn help(&self) -> Vec<String> {
+    let commands =
+      COMMANDS.iter().map(|&(_, cmd, about)| (cmd, about));
+    let tabs = (0..9).map(|i| (Command::SelectTab(i), "go to tab"));
+    let mut help: Vec<(&str, Vec<String>)> = vec![];
+    for (cmd, about) in commands.chain(tabs) {
+      let keys = self
+        .bindings
+        .iter()
+        .filter(|(_, c)| *c == cmd)
+        .map(|(key, _)| key.to_string());
+      match help.iter_mut().find(|(a, _)| *a == about) {
+        Some((_, bound)) => bound.extend(keys),
+        None => help.push((about, keys.collect())),
+      }
+    }
+    help
+      .into_iter()
+      .filter(|(_, keys)| !keys.is_empty())
+      .map(|(about, keys)| format!("{} - {about}", keys.join("/")))
+      .collect()
+  }
+}
+
+fn parse_command(name: &str) -> Result<Command> {
+  if let Some((_, cmd, _)) =
+    COMMANDS.iter().find(|(n, ..)| *n == name)
+  {
+    return Ok(*cmd);
+  }
+  name
+    .strip_prefix(TAB_PREFIX)
+    .and_then(|i| i.parse::<usize>().ok())
+    .filter(|i| (1..=9).contains(i))
+    .map(|i| Command::SelectTab(i - 1))
+    .with_context(|| format!("unknown command `{name}`"))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn keys(preset: Preset, bind: &[(&str, &str)]) -> Keys {
+    Keys {
+      preset,
+      bind: bind
+        .iter()
+        .map(|(k, c)| ((*k).to_owned(), (*c).to_owned()))
+        .collect(),
+    }
+  }
+
+  #[test]
+  fn should_map_chords_to_commands() {
+    let keymap = Keymap::new(&keys(
+      Preset::Vim,
+      &[("ctrl+z", "undo"), ("shift+e", "tab-2"), ("space", "none")],
+    ))
+    .unwrap();
+    let event = |code, modifiers| KeyEvent::new(code, modifiers);
+    let command =
+      |code, modifiers| keymap.command(event(code, modifiers));
+    assert!(
+      command(KeyCode::Char('z'), KeyModifiers::CONTROL)
+        == Some(Command::Undo)
+    );
+    assert!(
+      command(KeyCode::Char('E'), KeyModifiers::SHIFT)
+        == Some(Command::SelectTab(1))
+    );
+    assert!(
+      command(KeyCode::Char('l'), KeyModifiers::NONE)
+        == Some(Command::NextDate)
+    );
+    assert!(command(KeyCode::Char(' '), KeyModifiers::NONE).is_none());
+  }
+
+  #[test]
+  fn should_reject_conflicting_bindings() {
+    let same =
+      keys(Preset::Default, &[("U", "undo"), ("shift+u", "redo")]);
+    assert!(Keymap::new(&same).is_err());
+    let no_quit = keys(Preset::Default, &[("esc", "none")]);
+    assert!(Keymap::new(&no_quit).is_err());
+  }
+}
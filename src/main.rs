@@ -1,105 +1,249 @@
 mod app;
+mod cached_journal;
+mod cfg;
+mod cli;
 mod fs_journal;
+mod keymap;
+mod sqlite_journal;
+mod theme;
 mod tui;
 mod ui;
 
 use anyhow::Result;
-use crossterm::event::{KeyCode, KeyEvent};
-use ratatui::Frame;
+use crossterm::{
+  event::{KeyCode, KeyEvent, MouseEvent},
+  terminal,
+};
+use ratatui::{prelude::Rect, Frame};
 
 use self::{
   app::{App, Command},
+  cfg::Config,
+  cli::Cli,
+  theme::{Styles, Theme},
   tui::Tui,
 };
 
-mod cfg {
-  use std::path::PathBuf;
+fn main() -> Result<()> {
+  let cli = Cli::parse_args();
+  let journals_dir = cfg::journals_dir(cli.dir);
+  let cfg =
+    Config::load().map(|cfg| cfg.with_journals_dir(journals_dir));
+  let runtime = tokio::runtime::Builder::new_multi_thread()
+    .worker_threads(2)
+    .enable_all()
+    .build()?;
+  match cli.command {
+    Some(cmd) => runtime.block_on(cli::run(cmd, &cfg?)),
+    None => runtime.block_on(launch(cfg)),
+  }
+}
+
+async fn launch(cfg: Result<Config>) -> Result<()> {
+  let tui = Tui::try_new()?;
+  match cfg.and_then(App::new) {
+    Ok(app) => tui.launch(&mut app.init()).await?,
+    Err(err) => tui.launch(&mut ErrorScreen::new(&err)).await?,
+  }
+  Ok(())
+}
 
-  use crate::{app::journal::Journal, fs_journal::FsJournal};
+impl App {
+  fn handle_prompt_key_event(
+    &mut self,
+    k_event: KeyEvent,
+  ) -> Result<()> {
+    use app::prompt::Edit;
+    use Command::{
+      CancelPrompt, EditPrompt, ShiftPromptTime, SubmitPrompt,
+    };
+    use KeyCode::{
+      Backspace, Char, Delete, Down, End, Enter, Esc, Home, Left,
+      PageDown, PageUp, Right, Up,
+    };
 
-  pub struct Tab {
-    pub title: &'static str,
-    pub target: usize,
+    let edit = match k_event.code {
+      Esc => return self.handle_cmd(CancelPrompt),
+      Enter => return self.handle_cmd(SubmitPrompt),
+      Up => return self.handle_cmd(ShiftPromptTime(1)),
+      Down => return self.handle_cmd(ShiftPromptTime(-1)),
+      PageUp => return self.handle_cmd(ShiftPromptTime(5)),
+      PageDown => return self.handle_cmd(ShiftPromptTime(-5)),
+      Char(c) => Edit::Insert(c),
+      Backspace => Edit::Backspace,
+      Delete => Edit::Delete,
+      Left => Edit::Left,
+      Right => Edit::Right,
+      Home => Edit::Home,
+      End => Edit::End,
+      _ => return Ok(()),
+    };
+    self.handle_cmd(EditPrompt(edit))
   }
+}
 
-  pub fn tabs() -> Vec<Tab> {
-    [
-      Tab {
-        title: "Smokes",
-        target: 1,
+impl App {
+  fn handle_history_key_event(
+    &mut self,
+    k_event: KeyEvent,
+  ) -> Result<()> {
+    use Command::{
+      NextSelection, PrevSelection, Redo, ScrollHistoryDown,
+      ScrollHistoryUp, ToggleHistory, Undo,
+    };
+    use KeyCode::{Down, Esc, Up};
+
+    let cmd = match k_event.code {
+      Esc => ToggleHistory,
+      Up => ScrollHistoryUp,
+      Down => ScrollHistoryDown,
+      _ => match self.keymap().command(k_event) {
+        Some(cmd @ (ToggleHistory | Undo | Redo)) => cmd,
+        Some(PrevSelection) => ScrollHistoryUp,
+        Some(NextSelection) => ScrollHistoryDown,
+        _ => return Ok(()),
       },
-      Tab {
-        title: "Trains",
-        target: 4,
+    };
+    self.handle_cmd(cmd)
+  }
+}
+
+impl App {
+  fn handle_heatmap_key_event(
+    &mut self,
+    k_event: KeyEvent,
+  ) -> Result<()> {
+    use Command::{
+      MoveHeatmapCursor, NextDate, NextSelection, OpenHeatmapDate,
+      PrevDate, PrevSelection, ToggleHeatmap,
+    };
+    use KeyCode::{Down, Enter, Esc, Left, Right, Up};
+
+    let cmd = match k_event.code {
+      Esc => ToggleHeatmap,
+      Enter => OpenHeatmapDate,
+      Left => MoveHeatmapCursor(-7),
+      Right => MoveHeatmapCursor(7),
+      Up => MoveHeatmapCursor(-1),
+      Down => MoveHeatmapCursor(1),
+      _ => match self.keymap().command(k_event) {
+        Some(ToggleHeatmap) => ToggleHeatmap,
+        Some(PrevDate) => MoveHeatmapCursor(-7),
+        Some(NextDate) => MoveHeatmapCursor(7),
+        Some(PrevSelection) => MoveHeatmapCursor(-1),
+        Some(NextSelection) => MoveHeatmapCursor(1),
+        _ => return Ok(()),
       },
-    ]
-    .into()
+    };
+    self.handle_cmd(cmd)
   }
+}
 
-  pub fn journals_dir() -> PathBuf {
-    PathBuf::from(env!("HOME")).join(".journals")
+impl tui::App for App {
+  fn render(&self, f: &mut Frame) {
+    ui::render(&self.state(), f);
   }
 
-  pub fn journal(dir: impl Into<PathBuf>) -> Box<dyn Journal> {
-    Box::new(FsJournal::new(dir))
+  fn handle_key_event(&mut self, k_event: KeyEvent) -> Result<()> {
+    use Command::{DismissError, RetryFailed};
+    use KeyCode::{Char, Esc};
+
+    // Prompt input goes first, the error is dismissed after it
+    if self.state().prompt.is_some() {
+      return self.handle_prompt_key_event(k_event);
+    }
+    if self.state().error.is_some() {
+      match k_event.code {
+        Esc => return self.handle_cmd(DismissError),
+        Char('r') => return self.handle_cmd(RetryFailed),
+        _ => {}
+      }
+    }
+    if self.state().history_scroll.is_some() {
+      return self.handle_history_key_event(k_event);
+    }
+    if self.state().heatmap.is_some() {
+      return self.handle_heatmap_key_event(k_event);
+    }
+    match self.keymap().command(k_event) {
+      Some(cmd) => self.handle_cmd(cmd),
+      None => Ok(()),
+    }
   }
-}
 
-fn main() -> Result<()> {
-  tokio::runtime::Builder::new_multi_thread()
-    .worker_threads(2)
-    .enable_all()
-    .build()?
-    .block_on(launch())
+  fn handle_mouse_event(
+    &mut self,
+    m_event: MouseEvent,
+  ) -> Result<()> {
+    let state = self.state();
+    if state.error.is_some()
+      || state.prompt.is_some()
+      || state.history_scroll.is_some()
+      || state.heatmap.is_some()
+    {
+      return Ok(());
+    }
+    let (cols, rows) = terminal::size()?;
+    let size = Rect::new(0, 0, cols, rows);
+    let cmd = ui::command_at(&state, size, m_event);
+    drop(state);
+    match cmd {
+      Some(cmd) => self.handle_cmd(cmd),
+      None => Ok(()),
+    }
+  }
+
+  fn handle_resize(&mut self, size: Rect) -> Result<()> {
+    self.resize_days(ui::days_fit(size));
+    Ok(())
+  }
+
+  async fn changed(&mut self) {
+    self.changed().await;
+  }
+
+  fn should_quit(&self) -> bool {
+    self.should_quit()
+  }
 }
 
-async fn launch() -> Result<()> {
-  Tui::try_new()?.launch(&mut app().init()).await?;
-  Ok(())
+/// Fallback app shown instead of the journal when it can't start
+struct ErrorScreen {
+  message: String,
+  /// Default theme, as the config may be what failed
+  styles: Styles,
+  should_quit: bool,
 }
 
-fn app() -> App {
-  App::new(cfg::tabs().into_iter().map(
-    |cfg::Tab { title, target }| {
-      let journal =
-        cfg::journal(cfg::journals_dir().join(title.to_lowercase()));
-      (title, target, journal)
-    },
-  ))
+impl ErrorScreen {
+  fn new(err: &anyhow::Error) -> Self {
+    Self {
+      message: format!("{err:#}"),
+      styles: Styles::new(&Theme::default()),
+      should_quit: false,
+    }
+  }
 }
 
-impl tui::App for App {
+impl tui::App for ErrorScreen {
   fn render(&self, f: &mut Frame) {
-    ui::render(&self.state(), f);
+    ui::render_error(&self.styles, &self.message, f);
   }
 
   fn handle_key_event(&mut self, k_event: KeyEvent) -> Result<()> {
-    use Command::{
-      AddRecord, DeleteSelectedRecord, NextDate, NextSelection,
-      NextTab, PrevDate, PrevSelection, Quit, Redo, Undo,
-    };
-    use KeyCode::{Backspace, Char, Down, Esc, Left, Right, Tab, Up};
-
-    match k_event.code {
-      Esc => self.handle_cmd(Quit),
-      Tab => self.handle_cmd(NextTab),
-      Up => self.handle_cmd(PrevSelection),
-      Down => self.handle_cmd(NextSelection),
-      Left => self.handle_cmd(PrevDate),
-      Right => self.handle_cmd(NextDate),
-      Char(' ') => self.handle_cmd(AddRecord),
-      Backspace => self.handle_cmd(DeleteSelectedRecord),
-      Char('u') => self.handle_cmd(Undo),
-      Char('U') => self.handle_cmd(Redo),
-      _ => Ok(()),
+    if let KeyCode::Esc | KeyCode::Enter | KeyCode::Char('q') =
+      k_event.code
+    {
+      self.should_quit = true;
     }
+    Ok(())
   }
 
   async fn changed(&mut self) {
-    self.changed().await;
+    futures::future::pending().await
   }
 
   fn should_quit(&self) -> bool {
-    self.should_quit()
+    self.should_quit
   }
 }
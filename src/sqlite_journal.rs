@@ -0,0 +1,323 @@
+use std::{
+  collections::HashMap,
+  fs::create_dir_all,
+  path::Path,
+  sync::{Arc, Mutex},
+};
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Local, NaiveDate};
+use rusqlite::{Connection, Params};
+use tokio::task::spawn_blocking;
+
+use crate::app::{
+  journal::{days, fill_days, DayRecords, Journal, Stamp},
+  record::Record,
+};
+
+const SCHEMA: &str = "
+  CREATE TABLE IF NOT EXISTS records (
+    id INTEGER PRIMARY KEY,
+    date TEXT NOT NULL,
+    date_time TEXT NOT NULL
+  );
+  CREATE INDEX IF NOT EXISTS records_date ON records (date);
+  CREATE INDEX IF NOT EXISTS records_date_time ON records (date_time);
+";
+
+/// Columns added after the first release, created on open if missing
+const DETAILS_COLUMNS: [(&str, &str); 3] = [
+  ("quantity", "INTEGER"),
+  ("tags", "TEXT NOT NULL DEFAULT ''"),
+  ("note", "TEXT"),
+];
+
+const SELECT_RECORDS: &str = "
+  SELECT date_time, quantity, tags, note FROM records
+  WHERE date BETWEEN ?1 AND ?2
+";
+
+pub struct SqliteJournal {
+  conn: Arc<Mutex<Connection>>,
+}
+
+impl SqliteJournal {
+  pub fn open(path: &Path) -> Result<Self> {
+    if let Some(dir) = path.parent() {
+      create_dir_all(dir)?;
+    }
+    let conn = Connection::open(path)
+      .with_context(|| format!("can't open {}", path.display()))?;
+    conn.execute_batch(SCHEMA)?;
+    add_missing_columns(&conn)?;
+    Ok(Self {
+      conn: Arc::new(Mutex::new(conn)),
+    })
+  }
+
+  /// Adds records missing in the journal in a single transaction,
+  /// returns count of imported ones
+  pub async fn import(&self, recs: DayRecords) -> Result<usize> {
+    self
+      .call(move |conn| {
+        let tx = conn.transaction()?;
+        let mut imported = 0;
+        {
+          let mut insert = tx.prepare(
+            "INSERT INTO records (date, date_time, quantity, tags, note)
+             SELECT ?1, ?2, ?3, ?4, ?5 WHERE NOT EXISTS
+              (SELECT 1 FROM records WHERE date_time = ?2)",
+          )?;
+          for rec in recs {
+            imported += insert.execute(record_params(&rec))?;
+          }
+        }
+        tx.commit()?;
+        Ok(imported)
+      })
+      .await
+  }
+
+  async fn call<T, F>(&self, f: F) -> Result<T>
+  where
+    T: Send + 'static,
+    F: FnOnce(&mut Connection) -> Result<T> + Send + 'static,
+  {
+    let conn = self.conn.clone();
+    spawn_blocking(move || {
+      let mut conn = conn.lock().expect("poisoned connection");
+      f(&mut conn)
+    })
+    .await?
+  }
+}
+
+#[async_trait]
+impl Journal for SqliteJournal {
+  async fn day_records(&self, date: NaiveDate) -> Result<DayRecords> {
+    self.records_between(date, date).await
+  }
+
+  async fn add(&self, rec: Record) -> Result<()> {
+    self
+      .call(move |conn| {
+        let tx = conn.transaction()?;
+        tx.execute(
+          "INSERT INTO records (date, date_time, quantity, tags, note)
+           VALUES (?1, ?2, ?3, ?4, ?5)",
+          record_params(&rec),
+        )?;
+        tx.commit()?;
+        Ok(())
+      })
+      .await
+  }
+
+  async fn remove(&self, dt: DateTime<Local>) -> Result<()> {
+    self
+      .call(move |conn| {
+        let tx = conn.transaction()?;
+        tx.execute(
+          "DELETE FROM records WHERE date_time = ?1",
+          [dt.to_rfc3339()],
+        )?;
+        tx.commit()?;
+        Ok(())
+      })
+      .await
+  }
+
+  async fn update(&self, rec: Record) -> Result<()> {
+    self
+      .call(move |conn| {
+        let tx = conn.transaction()?;
+        tx.execute(
+          "UPDATE records SET quantity = ?3, tags = ?4, note = ?5
+           WHERE date = ?1 AND date_time = ?2",
+          record_params(&rec),
+        )?;
+        tx.commit()?;
+        Ok(())
+      })
+      .await
+  }
+
+  async fn move_record(
+    &self,
+    from: DateTime<Local>,
+    to: Record,
+  ) -> Result<()> {
+    self
+      .call(move |conn| {
+        let tx = conn.transaction()?;
+        tx.execute(
+          "DELETE FROM records WHERE date_time = ?1",
+          [from.to_rfc3339()],
+        )?;
+        tx.execute(
+          "INSERT INTO records (date, date_time, quantity, tags, note)
+           VALUES (?1, ?2, ?3, ?4, ?5)",
+          record_params(&to),
+        )?;
+        tx.commit()?;
+        Ok(())
+      })
+      .await
+  }
+
+  async fn records_between(
+    &self,
+    start: NaiveDate,
+    end: NaiveDate,
+  ) -> Result<DayRecords> {
+    let mut recs = self
+      .call(move |conn| {
+        let mut select = conn.prepare_cached(SELECT_RECORDS)?;
+        let rows = select.query_map(
+          [start.to_string(), end.to_string()],
+          |row| {
+            Ok((
+              row.get::<_, String>(0)?,
+              row.get::<_, Option<u32>>(1)?,
+              row.get::<_, String>(2)?,
+              row.get::<_, Option<String>>(3)?,
+            ))
+          },
+        )?;
+        rows
+          .map(|row| {
+            let (date_time, quantity, tags, note) = row?;
+            Ok(Record {
+              quantity,
+              tags: tags.split_whitespace().map(Into::into).collect(),
+              note,
+              ..Record::new(parse_date_time(&date_time)?)
+            })
+          })
+          .collect::<Result<DayRecords>>()
+      })
+      .await?;
+    recs.sort_unstable();
+    Ok(recs)
+  }
+
+  async fn count_by_day(
+    &self,
+    start: NaiveDate,
+    end: NaiveDate,
+  ) -> Result<Vec<(NaiveDate, usize)>> {
+    let counts = self
+      .call(move |conn| {
+        let mut select = conn.prepare_cached(
+          "SELECT date, SUM(COALESCE(quantity, 1)) FROM records
+           WHERE date BETWEEN ?1 AND ?2 GROUP BY date",
+        )?;
+        let rows = select
+          .query_map([start.to_string(), end.to_string()], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?))
+          })?;
+        rows
+          .map(|row| {
+            let (date, count) = row?;
+            let date = date.parse().context("date parse error")?;
+            Ok((date, usize::try_from(count)?))
+          })
+          .collect::<Result<HashMap<NaiveDate, usize>>>()
+      })
+      .await?;
+    Ok(fill_days(start, end, &counts))
+  }
+
+  /// Every day gets the data version, which changes on commits of
+  /// other connections
+  async fn stamps_between(
+    &self,
+    start: NaiveDate,
+    end: NaiveDate,
+  ) -> Result<Option<HashMap<NaiveDate, Stamp>>> {
+    let version = self
+      .call(|conn| {
+        Ok(
+          conn
+            .query_row("PRAGMA data_version", [], |row| row.get(0))?,
+        )
+      })
+      .await?;
+    let stamps =
+      days(start, end).map(|date| (date, Stamp::Version(version)));
+    Ok(Some(stamps.collect()))
+  }
+}
+
+fn add_missing_columns(conn: &Connection) -> Result<()> {
+  for (name, definition) in DETAILS_COLUMNS {
+    let exists: bool = conn.query_row(
+      "SELECT COUNT(*) > 0 FROM pragma_table_info('records')
+       WHERE name = ?1",
+      [name],
+      |row| row.get(0),
+    )?;
+    if !exists {
+      conn.execute_batch(&format!(
+        "ALTER TABLE records ADD COLUMN {name} {definition}"
+      ))?;
+    }
+  }
+  Ok(())
+}
+
+fn record_params(rec: &Record) -> impl Params {
+  (
+    rec.time.date_naive().to_string(),
+    rec.time.to_rfc3339(),
+    rec.quantity,
+    rec.tags.join(" "),
+    rec.note.clone(),
+  )
+}
+
+fn parse_date_time(s: &str) -> Result<DateTime<Local>> {
+  let dt =
+    DateTime::parse_from_rfc3339(s).context("date parse error")?;
+  Ok(dt.with_timezone(&Local))
+}
+
+#[cfg(test)]
+mod tests {
+  use chrono::{Duration, TimeZone};
+
+  use super::*;
+
+  #[tokio::test]
+  async fn should_add_import_and_remove_records() {
+    let journal = SqliteJournal::open(Path::new(":memory:")).unwrap();
+    let noon = Local.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap();
+    let earlier = Record::new(noon - Duration::hours(1));
+    let later = Record::new(noon + Duration::hours(1))
+      .with_details("x3 #leg // after coffee")
+      .unwrap();
+    journal.add(Record::new(noon)).await.unwrap();
+    let imported = journal
+      .import(vec![Record::new(noon), earlier.clone()])
+      .await
+      .unwrap();
+    assert_eq!(1, imported);
+    journal.add(Record::new(later.time)).await.unwrap();
+    journal.update(later.clone()).await.unwrap();
+    journal.remove(noon).await.unwrap();
+    let moved = Record::new(noon - Duration::days(1));
+    journal.add(moved.clone()).await.unwrap();
+    journal
+      .move_record(moved.time, Record::new(noon))
+      .await
+      .unwrap();
+    journal.move_record(noon, moved).await.unwrap();
+    let recs = journal.day_records(noon.date_naive()).await.unwrap();
+    assert_eq!(vec![earlier, later], recs);
+    let date = noon.date_naive();
+    let yesterday = date.pred_opt().unwrap();
+    let counts = journal.count_by_day(yesterday, date).await.unwrap();
+    assert_eq!(vec![(yesterday, 1), (date, 4)], counts);
+  }
+}
@@ -0,0 +1,223 @@
+use std::env;
+
+use ratatui::style::{Color, Style, Stylize};
+use serde::{Deserialize, Serialize};
+
+/// `[theme]` section of the config
+#[derive(Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Theme {
+  #[serde(default, skip_serializing_if = "Palette::is_default")]
+  pub palette: Palette,
+  /// Colours of the terminal, guessed from environment by default
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub colors: Option<Colors>,
+}
+
+impl Theme {
+  pub fn is_default(&self) -> bool {
+    self == &Self::default()
+  }
+}
+
+#[derive(Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Palette {
+  #[default]
+  Dark,
+  Light,
+  HighContrast,
+}
+
+impl Palette {
+  fn is_default(&self) -> bool {
+    self == &Self::default()
+  }
+}
+
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Colors {
+  #[serde(rename = "none")]
+  None,
+  #[serde(rename = "16")]
+  Ansi16,
+  #[serde(rename = "256")]
+  Ansi256,
+  #[serde(rename = "true")]
+  True,
+}
+
+impl Colors {
+  /// Colours by `NO_COLOR`, `COLORTERM` and `TERM` variables
+  fn detect() -> Self {
+    let var = |name| env::var(name).unwrap_or_default();
+    if !var("NO_COLOR").is_empty() {
+      Colors::None
+    } else if matches!(
+      var("COLORTERM").as_str(),
+      "truecolor" | "24bit"
+    ) {
+      Colors::True
+    } else if var("TERM").contains("256") {
+      Colors::Ansi256
+    } else {
+      Colors::Ansi16
+    }
+  }
+}
+
+/// Styles of the UI resolved from [`Theme`] for the terminal
+#[derive(Clone, Copy, PartialEq)]
+pub struct Styles {
+  pub primary: Style,
+  pub secondary: Style,
+  pub accent: Style,
+  pub grey: Style,
+  pub red: Style,
+  colors: Colors,
+}
+
+impl Default for Styles {
+  fn default() -> Self {
+    Self::with_colors(Palette::default(), Colors::True)
+  }
+}
+
+impl Styles {
+  /// Styles of `theme`, without colours if `NO_COLOR` is set
+  pub fn new(theme: &Theme) -> Self {
+    let colors = match (Colors::detect(), theme.colors) {
+      (Colors::None, _) | (_, Some(Colors::None)) => Colors::None,
+      (detected, colors) => colors.unwrap_or(detected),
+    };
+    Self::with_colors(theme.palette, colors)
+  }
+
+  fn with_colors(palette: Palette, colors: Colors) -> Self {
+    if colors == Colors::None {
+      return Self {
+        primary: Style::new(),
+        secondary: Style::new(),
+        accent: Style::new().bold(),
+        grey: Style::new().dim(),
+        red: Style::new(),
+        colors,
+      };
+    }
+    let [primary, secondary, accent, grey, red] = match palette {
+      Palette::Dark => [
+        Color::Yellow,
+        Color::Cyan,
+        Color::Green,
+        Color::DarkGray,
+        Color::Red,
+      ],
+      Palette::Light => [
+        Color::Black,
+        Color::Blue,
+        Color::Magenta,
+        Color::DarkGray,
+        Color::Red,
+      ],
+      Palette::HighContrast => [
+        Color::White,
+        Color::LightCyan,
+        Color::LightYellow,
+        Color::Gray,
+        Color::LightRed,
+      ],
+    };
+    Self {
+      primary: Style::new().fg(primary),
+      secondary: Style::new().fg(secondary),
+      accent: Style::new().fg(accent),
+      grey: Style::new().fg(grey),
+      red: Style::new().fg(red),
+      colors,
+    }
+  }
+
+  /// Styles with the accent of `color` if any
+  pub fn with_accent(mut self, color: Option<Color>) -> Self {
+    if let Some(color) = color {
+      self.accent = self.accent.fg(self.fit(color));
+    }
+    self
+  }
+
+  /// `color` or the closest one the terminal shows
+  pub fn fit(&self, color: Color) -> Color {
+    match (self.colors, color) {
+      (Colors::None, _) => Color::Reset,
+      (Colors::Ansi16, Color::Rgb(r, g, b)) => closest_ansi(r, g, b),
+      (Colors::Ansi256, Color::Rgb(r, g, b)) => {
+        let [r, g, b] = [r, g, b].map(cube_level);
+        Color::Indexed(16 + 36 * r + 6 * g + b)
+      }
+      _ => color,
+    }
+  }
+}
+
+/// Level of `value` in the 6×6×6 cube of 256 colours
+fn cube_level(value: u8) -> u8 {
+  match value {
+    0..=47 => 0,
+    48..=114 => 1,
+    _ => (value - 35) / 40,
+  }
+}
+
+fn closest_ansi(r: u8, g: u8, b: u8) -> Color {
+  const ANSI: [(Color, [u8; 3]); 16] = [
+    (Color::Black, [0, 0, 0]),
+    (Color::Red, [128, 0, 0]),
+    (Color::Green, [0, 128, 0]),
+    (Color::Yellow, [128, 128, 0]),
+    (Color::Blue, [0, 0, 128]),
+    (Color::Magenta, [128, 0, 128]),
+    (Color::Cyan, [0, 128, 128]),
+    (Color::Gray, [192, 192, 192]),
+    (Color::DarkGray, [128, 128, 128]),
+    (Color::LightRed, [255, 0, 0]),
+    (Color::LightGreen, [0, 255, 0]),
+    (Color::LightYellow, [255, 255, 0]),
+    (Color::LightBlue, [0, 0, 255]),
+    (Color::LightMagenta, [255, 0, 255]),
+    (Color::LightCyan, [0, 255, 255]),
+    (Color::White, [255, 255, 255]),
+  ];
+  let distance = |rgb: &[u8; 3]| -> u32 {
+    [r, g, b]
+      .iter()
+      .zip(rgb)
+      .map(|(a, b)| u32::from(a.abs_diff(*b)).pow(2))
+      .sum()
+  };
+  ANSI
+    .iter()
+    .min_by_key(|(_, rgb)| distance(rgb))
+    .map(|(color, _)| *color)
+    .expect("ansi colours")
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn should_fit_colors_to_terminal() {
+    let orange = Color::Rgb(255, 140, 0);
+    let fit =
+      |colors| Styles::with_colors(Palette::Dark, colors).fit(orange);
+    assert_eq!(orange, fit(Colors::True));
+    assert_eq!(Color::Indexed(208), fit(Colors::Ansi256));
+    assert_eq!(Color::LightYellow, fit(Colors::Ansi16));
+    assert_eq!(Color::Reset, fit(Colors::None));
+    assert_eq!(
+      Color::Cyan,
+      Styles::with_colors(Palette::Dark, Colors::Ansi16)
+        .fit(Color::Cyan)
+    );
+  }
+}
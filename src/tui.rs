@@ -6,11 +6,13 @@ use anyhow::Result;
 use crossterm::{
   event::{
     DisableMouseCapture, EnableMouseCapture, KeyCode, KeyEvent,
-    KeyModifiers,
+    KeyModifiers, MouseEvent,
   },
   terminal::{self, EnterAlternateScreen, LeaveAlternateScreen},
 };
-use ratatui::{backend::CrosstermBackend, Frame, Terminal};
+use ratatui::{
+  backend::CrosstermBackend, prelude::Rect, Frame, Terminal,
+};
 
 use self::event_listener::EventListener;
 
@@ -19,6 +21,8 @@ type Crossterm = Terminal<CrosstermBackend<io::Stderr>>;
 #[derive(Clone, Debug)]
 pub enum Event {
   KeyPress(KeyEvent),
+  /// Left click or scroll
+  Mouse(MouseEvent),
   Resize,
   Error(String),
 }
@@ -26,6 +30,16 @@ pub enum Event {
 pub trait App {
   fn render(&self, f: &mut Frame);
   fn handle_key_event(&mut self, k_event: KeyEvent) -> Result<()>;
+  fn handle_mouse_event(
+    &mut self,
+    _m_event: MouseEvent,
+  ) -> Result<()> {
+    Ok(())
+  }
+  /// Called before rendering at start and on every resize
+  fn handle_resize(&mut self, _size: Rect) -> Result<()> {
+    Ok(())
+  }
   async fn changed(&mut self);
   fn should_quit(&self) -> bool;
 }
@@ -83,11 +97,17 @@ impl Tui {
   }
 
   async fn run(&mut self, app: &mut impl App) -> Result<()> {
+    app.handle_resize(self.terminal.size()?)?;
+    self.render(app)?;
     while !app.should_quit() {
       tokio::select! {
         () = app.changed() => self.render(app)?,
         event = self.events.next() => match event? {
-          Event::Resize => self.render(app)?,
+          Event::Resize => {
+            app.handle_resize(self.terminal.size()?)?;
+            self.render(app)?;
+          }
+          Event::Mouse(m_event) => app.handle_mouse_event(m_event)?,
           Event::Error(error) => return Err(anyhow::anyhow!(error)),
           Event::KeyPress(k_event) => match k_event.code {
             KeyCode::Char('c' | 'C') if is_ctrl(k_event) => break,
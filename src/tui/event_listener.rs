@@ -1,5 +1,7 @@
 use anyhow::{Context, Result};
-use crossterm::event::{Event, EventStream, KeyEventKind};
+use crossterm::event::{
+  Event, EventStream, KeyEventKind, MouseButton, MouseEventKind,
+};
 use futures::{FutureExt, StreamExt};
 use tokio::sync::mpsc::{self, error::SendError};
 
@@ -52,6 +54,16 @@ fn handle_crossterm_event(
     Ok(Event::Key(key)) if key.kind == KeyEventKind::Press => {
       tx.send(TuiEvent::KeyPress(key))?;
     }
+    Ok(Event::Mouse(mouse))
+      if matches!(
+        mouse.kind,
+        MouseEventKind::Down(MouseButton::Left)
+          | MouseEventKind::ScrollUp
+          | MouseEventKind::ScrollDown
+      ) =>
+    {
+      tx.send(TuiEvent::Mouse(mouse))?;
+    }
     Ok(Event::Resize(_, _)) => {
       tx.send(TuiEvent::Resize)?;
     }
@@ -1,26 +1,49 @@
+mod hit;
 mod layout;
-mod styles;
 mod widgets;
 
 use ratatui::{
-  prelude::{Alignment, Frame},
+  prelude::{Alignment, Constraint, Frame, Rect},
   style::Stylize,
-  widgets::{Block, BorderType, Borders, Padding},
+  widgets::{Block, BorderType, Borders, Clear, Padding, Wrap},
 };
 
-use crate::app::State;
+use chrono::NaiveDate;
 
+use crate::{
+  app::{prompt::Prompt, State},
+  theme::Styles,
+};
+
+use self::widgets::DaysBarChart;
+
+pub use self::hit::command_at;
 use self::layout::Body;
 
 const CARD: Block<'_> = Block::new()
   .padding(Padding::horizontal(1))
   .borders(Borders::ALL)
-  .border_type(BorderType::Rounded)
-  .border_style(styles::SECONDARY);
+  .border_type(BorderType::Rounded);
+
+/// Count of days fitting the days chart of the journal view
+pub fn days_fit(size: Rect) -> u64 {
+  let days = CARD.inner(Body::new(size).days);
+  let step = DaysBarChart::BAR_WIDTH + DaysBarChart::GAP;
+  ((days.width + DaysBarChart::GAP) / step).into()
+}
+
+fn card(s: &Styles) -> Block<'static> {
+  CARD.border_style(s.secondary)
+}
 
 pub fn render(state: &State, f: &mut Frame) {
+  let s = state.styles();
+  if layout::too_small(f.size()) {
+    return render_too_small(&s, f);
+  }
   let Body {
     tabs,
+    journals_dir,
     date,
     list,
     level,
@@ -33,54 +56,187 @@ pub fn render(state: &State, f: &mut Frame) {
 
   f.render_widget(
     widgets::tabs(state)
-      .style(styles::PRIMARY)
-      .highlight_style(styles::SECONDARY.bold().underlined()),
+      .style(s.primary)
+      .highlight_style(s.secondary.bold().underlined()),
     tabs,
   );
 
   f.render_widget(
-    widgets::date_paragraph(state.date)
-      .block(CARD)
-      .style(styles::ACCENT.bold()),
+    widgets::journals_dir_paragraph(state)
+      .style(s.grey)
+      .alignment(Alignment::Right),
+    journals_dir,
+  );
+
+  f.render_widget(
+    widgets::date_paragraph(state.date, state.period)
+      .block(card(&s))
+      .style(s.accent.bold()),
     date,
   );
 
   f.render_widget(
     widgets::record_list(state)
-      .block(CARD.borders(Borders::ALL ^ Borders::TOP))
-      .style(styles::PRIMARY),
+      .block(card(&s).borders(Borders::ALL ^ Borders::TOP))
+      .style(s.primary),
     list,
   );
 
   f.render_widget(
     widgets::level_bar(state)
-      .block(CARD.title(st("Vol.")))
-      .style(styles::PRIMARY),
+      .block(card(&s).title(st("Vol.")))
+      .style(s.primary),
     level,
   );
 
   f.render_widget(
-    widgets::DaysBarChart::new(state).block(CARD.title(st("Days"))),
+    widgets::DaysBarChart::new(state)
+      .block(card(&s).title(st("Days"))),
     days,
   );
 
   f.render_widget(
     widgets::time_smoke_records_bar_chart(state)
-      .block(CARD.title(st("Time")).padding(Padding::uniform(1)))
-      .style(styles::PRIMARY),
+      .block(card(&s).title(st("Time")).padding(Padding::uniform(1)))
+      .style(s.primary),
     time,
   );
 
   f.render_widget(
     widgets::year_smoke_records_bar_chart(state)
-      .block(CARD.title(st("Months")).padding(Padding::horizontal(4)))
-      .style(styles::PRIMARY),
+      .block(
+        card(&s).title(st("Months")).padding(Padding::horizontal(4)),
+      )
+      .style(s.primary),
     year,
   );
 
   f.render_widget(
-    widgets::help_paragraph()
-      .style(styles::GREY)
+    widgets::help_paragraph(state, help)
+      .style(s.grey)
+      .alignment(Alignment::Center),
+    help,
+  );
+
+  if let Some(cursor) = state.heatmap {
+    render_heatmap(state, cursor, f);
+  }
+
+  if let Some(scroll) = state.history_scroll {
+    render_history(state, scroll, f);
+  }
+
+  if let Some(prompt) = &state.prompt {
+    render_prompt(state, prompt, f);
+  }
+
+  if let Some(error) = &state.error {
+    render_error_banner(&s, error, f);
+  }
+}
+
+fn render_too_small(s: &Styles, f: &mut Frame) {
+  let size = f.size();
+  let [_, message, _] = layout::vsplit(
+    [
+      Constraint::Min(0),
+      Constraint::Length(2),
+      Constraint::Min(0),
+    ],
+    size,
+  );
+  f.render_widget(
+    widgets::too_small_paragraph(size.width, size.height)
+      .style(s.primary)
+      .alignment(Alignment::Center)
+      .wrap(Wrap { trim: false }),
+    message,
+  );
+}
+
+fn render_error_banner(s: &Styles, message: &str, f: &mut Frame) {
+  let area = layout::popup(f.size(), 72, 8);
+  let [message_area, help] = layout::vsplit(
+    [Constraint::Min(1), Constraint::Length(1)],
+    CARD.inner(area),
+  );
+
+  f.render_widget(Clear, area);
+  f.render_widget(
+    card(s).title(st("Error")).border_style(s.red),
+    area,
+  );
+  f.render_widget(
+    widgets::error_paragraph(message)
+      .style(s.primary)
+      .wrap(Wrap { trim: false }),
+    message_area,
+  );
+  f.render_widget(
+    widgets::error_banner_help_paragraph().style(s.grey),
+    help,
+  );
+}
+
+fn render_heatmap(state: &State, cursor: NaiveDate, f: &mut Frame) {
+  let s = state.styles();
+  let area = layout::under_tabs(f.size());
+
+  f.render_widget(Clear, area);
+  f.render_widget(
+    widgets::Heatmap::new(state, cursor)
+      .block(card(&s).title(st("Heatmap"))),
+    area,
+  );
+}
+
+fn render_history(state: &State, scroll: usize, f: &mut Frame) {
+  let s = state.styles();
+  let area = layout::popup(f.size(), 72, 22);
+
+  f.render_widget(Clear, area);
+  f.render_widget(
+    widgets::history_list(state, scroll)
+      .block(card(&s).title(st("History")))
+      .style(s.primary),
+    area,
+  );
+}
+
+fn render_prompt(state: &State, prompt: &Prompt, f: &mut Frame) {
+  let s = state.styles();
+  let area = layout::popup(f.size(), 72, 5);
+  let block = card(&s).title(st(prompt.kind.title()));
+  let input = block.inner(area);
+
+  f.render_widget(Clear, area);
+  f.render_widget(
+    widgets::prompt_paragraph(state, prompt).block(block),
+    area,
+  );
+
+  #[allow(clippy::cast_possible_truncation)]
+  let cursor = prompt.cursor as u16;
+  f.set_cursor(
+    (input.x + cursor).min(input.right().saturating_sub(1)),
+    input.y,
+  );
+}
+
+pub fn render_error(s: &Styles, message: &str, f: &mut Frame) {
+  let (area, help) = layout::error(f.size());
+
+  f.render_widget(
+    widgets::error_paragraph(message)
+      .block(card(s).title(st("Error")).border_style(s.red))
+      .style(s.primary)
+      .wrap(Wrap { trim: false }),
+    area,
+  );
+
+  f.render_widget(
+    widgets::error_help_paragraph()
+      .style(s.grey)
       .alignment(Alignment::Center),
     help,
   );
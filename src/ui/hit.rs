@@ -0,0 +1,85 @@
+use chrono::NaiveDate;
+use crossterm::event::{MouseEvent, MouseEventKind};
+use ratatui::{prelude::Rect, text::Line, widgets::Borders};
+
+use crate::app::{Command, State};
+
+use super::{
+  layout::{self, Body},
+  widgets::{self, DaysBarChart},
+  CARD,
+};
+
+/// Command of the click or scroll at the journal view of `size`
+pub fn command_at(
+  state: &State,
+  size: Rect,
+  event: MouseEvent,
+) -> Option<Command> {
+  if layout::too_small(size) {
+    return None;
+  }
+  let Body {
+    tabs,
+    date,
+    list,
+    days,
+    ..
+  } = Body::new(size);
+  let (x, y) = (event.column, event.row);
+  let contains = |area: Rect| {
+    (area.left()..area.right()).contains(&x)
+      && (area.top()..area.bottom()).contains(&y)
+  };
+  let scroll = |prev, next| match event.kind {
+    MouseEventKind::ScrollUp => Some(prev),
+    MouseEventKind::ScrollDown => Some(next),
+    _ => None,
+  };
+  let click = matches!(event.kind, MouseEventKind::Down(_));
+
+  if contains(tabs) && click {
+    tab_at(state, tabs, x).map(Command::SelectTab)
+  } else if contains(date) {
+    scroll(Command::PrevDate, Command::NextDate)
+  } else if contains(list) {
+    let inner = CARD.borders(Borders::ALL ^ Borders::TOP).inner(list);
+    let i = usize::from(y.checked_sub(inner.y)?);
+    if click {
+      (contains(inner) && i < state.list.len())
+        .then_some(Command::SelectRecord(i))
+    } else {
+      scroll(Command::PrevSelection, Command::NextSelection)
+    }
+  } else if contains(days) && click {
+    let inner = CARD.inner(days);
+    contains(inner)
+      .then(|| day_at(state, inner, x))
+      .flatten()
+      .map(Command::SelectDate)
+  } else {
+    None
+  }
+}
+
+/// Mirrors layout of [`ratatui::widgets::Tabs`]: a space around
+/// every title
+fn tab_at(state: &State, area: Rect, x: u16) -> Option<usize> {
+  let mut left = area.x + 1;
+  for (i, title) in state.tabs.iter().enumerate() {
+    let width = Line::from(widgets::tab_label(title)).width() as u16;
+    if (left..left + width).contains(&x) {
+      return Some(i);
+    }
+    left += width + 2;
+  }
+  None
+}
+
+/// Date of the bar or weekday at column `x` of the chart `area`,
+/// gaps belong to the bar before
+fn day_at(state: &State, area: Rect, x: u16) -> Option<NaiveDate> {
+  let step = DaysBarChart::BAR_WIDTH + DaysBarChart::GAP;
+  let i = usize::from((x - area.x) / step);
+  state.recs_by_date.get(i).map(|(date, _)| *date)
+}
@@ -2,6 +2,7 @@ use ratatui::prelude::{Constraint, Direction, Layout, Rect};
 
 pub struct Body {
   pub tabs: Rect,
+  pub journals_dir: Rect,
   pub date: Rect,
   pub list: Rect,
   pub level: Rect,
@@ -11,28 +12,69 @@ pub struct Body {
   pub help: Rect,
 }
 
+/// Smaller terminals get a warning instead of the journal view
+pub const MIN_WIDTH: u16 = 40;
+pub const MIN_HEIGHT: u16 = 16;
+/// Narrower terminals hide the time card
+const WIDE: u16 = 80;
+/// Shorter terminals hide the year card and get a line of help
+const TALL: u16 = 36;
+/// Least height of the cards above the year card
+const MAIN_HEIGHT: u16 = 28;
+/// Width kept for the time card right of the days chart
+const TIME_WIDTH: u16 = 38;
+/// Width of the date, list and level cards above the days chart,
+/// the level card is hidden when narrower
+const MIN_DAYS_WIDTH: u16 = 42;
+
 impl Body {
+  /// Collapsed cards get empty rects
   pub fn new(size: Rect) -> Self {
     use Constraint::{Length, Min, Percentage};
 
-    let [tabs, date_list_level_days_time, year, help] =
-      vsplit([Length(1), Length(28), Min(5), Length(0)], size);
+    let (main_height, help_height) = if size.height >= TALL {
+      (MAIN_HEIGHT.max((size.height - 3) * 2 / 3), 2)
+    } else {
+      (size.height.saturating_sub(2), 1)
+    };
+    let [tabs_journals_dir, date_list_level_days_time, year, help] =
+      vsplit(
+        [Length(1), Length(main_height), Min(0), Length(help_height)],
+        size,
+      );
+
+    let [tabs, journals_dir] =
+      hsplit([Percentage(50), Percentage(50)], tabs_journals_dir);
 
-    let [date_list_level_days, _, time] = hsplit(
-      [Length(42), Length(1), Min(10)],
-      date_list_level_days_time,
-    );
+    let (date_list_level_days, time) = if size.width >= WIDE {
+      let days_width =
+        MIN_DAYS_WIDTH.max(size.width.saturating_sub(TIME_WIDTH));
+      let [date_list_level_days, _, time] = hsplit(
+        [Length(days_width), Length(1), Min(10)],
+        date_list_level_days_time,
+      );
+      (date_list_level_days, time)
+    } else {
+      (date_list_level_days_time, Rect::default())
+    };
 
     let [date_list_level, days] =
       vsplit([Percentage(50), Percentage(50)], date_list_level_days);
 
-    let [date_list, _, level] =
-      hsplit([Length(24), Length(1), Min(17)], date_list_level);
+    let (date_list, level) =
+      if date_list_level.width >= MIN_DAYS_WIDTH {
+        let [date_list, _, level] =
+          hsplit([Min(24), Length(1), Length(17)], date_list_level);
+        (date_list, level)
+      } else {
+        (date_list_level, Rect::default())
+      };
 
     let [date, list] = vsplit([Length(3), Min(1)], date_list);
 
     Self {
       tabs,
+      journals_dir,
       date,
       list,
       level,
@@ -44,6 +86,40 @@ impl Body {
   }
 }
 
+/// Whether `size` can't fit the journal view
+pub fn too_small(size: Rect) -> bool {
+  size.width < MIN_WIDTH || size.height < MIN_HEIGHT
+}
+
+pub fn error(size: Rect) -> (Rect, Rect) {
+  use Constraint::{Length, Min, Percentage};
+
+  let [_, card, help, _] =
+    vsplit([Percentage(20), Min(5), Length(1), Percentage(20)], size);
+  let [_, card, _] =
+    hsplit([Percentage(10), Percentage(80), Percentage(10)], card);
+  (card, help)
+}
+
+/// Whole `size` but the tabs line, for full-screen views
+pub fn under_tabs(size: Rect) -> Rect {
+  let [_, area] =
+    vsplit([Constraint::Length(1), Constraint::Min(0)], size);
+  area
+}
+
+/// Centered rect of the given width and height fitting into `size`
+pub fn popup(size: Rect, width: u16, height: u16) -> Rect {
+  let width = width.min(size.width);
+  let height = height.min(size.height);
+  Rect::new(
+    size.x + (size.width - width) / 2,
+    size.y + (size.height - height) / 2,
+    width,
+    height,
+  )
+}
+
 pub fn vsplit<const N: usize>(
   constraints: [Constraint; N],
   size: Rect,
@@ -76,3 +152,23 @@ fn destruct_layout<const N: usize>(layout: &[Rect]) -> [Rect; N] {
     rect
   })
 }
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn should_collapse_cards_on_small_terminals() {
+    let body = Body::new(Rect::new(0, 0, 80, 40));
+    assert_eq!((42, 37), (body.days.width, body.time.width));
+    assert_eq!((17, 2), (body.level.width, body.help.height));
+    assert!(body.year.height >= 5);
+    let body = Body::new(Rect::new(0, 0, 60, 20));
+    assert_eq!(60, body.days.width);
+    assert_eq!((0, 0), (body.time.area(), body.year.area()));
+    assert_eq!(1, body.help.height);
+    let body = Body::new(Rect::new(0, 0, 41, 20));
+    assert_eq!((41, 0), (body.list.width, body.level.area()));
+    assert!(too_small(Rect::new(0, 0, 39, 20)));
+  }
+}
@@ -1,7 +0,0 @@
-use ratatui::style::{Color, Style};
-
-pub const PRIMARY: Style = Style::new().fg(Color::Yellow);
-pub const SECONDARY: Style = Style::new().fg(Color::Cyan);
-pub const ACCENT: Style = Style::new().fg(Color::Green);
-pub const GREY: Style = Style::new().fg(Color::DarkGray);
-pub const RED: Style = Style::new().fg(Color::Red);
@@ -1,7 +1,8 @@
-use std::cmp::max;
+use std::{cmp::max, collections::HashMap};
 
 use chrono::{
-  Datelike, Local, Month, NaiveDate, NaiveTime, Timelike, Weekday,
+  Datelike, Days, Local, Month, NaiveDate, NaiveTime, Timelike,
+  Weekday,
 };
 use ratatui::{
   prelude::{Buffer, Constraint, Direction, Rect},
@@ -13,41 +14,137 @@ use ratatui::{
   },
 };
 
-use crate::app::{level::Level, State};
+use crate::{
+  app::{
+    level::Level,
+    period::Period,
+    prompt::{Prompt, PromptKind},
+    tab::HEATMAP_WEEKS,
+    State, TabTitle,
+  },
+  theme::Styles,
+};
 
-use super::{layout, styles};
+use super::layout;
 
 pub fn tabs(state: &State) -> Tabs<'_> {
+  let s = state.styles();
   let titles = state
     .tabs
     .iter()
-    .map(|title| format!("[ {title} ]"))
+    .map(|title| {
+      let text = tab_label(title);
+      match title.color {
+        Some(color) => Line::styled(text, s.primary.fg(s.fit(color))),
+        None => Line::from(text),
+      }
+    })
     .collect();
   Tabs::new(titles).divider("").select(state.tabs.selected())
 }
 
-pub fn date_paragraph<'a>(date: NaiveDate) -> Paragraph<'a> {
-  Paragraph::new(format!("< {} >", date.format("%a, %-d %b %Y")))
+pub fn tab_label(title: &TabTitle) -> String {
+  format!("[ {} ]", title.text)
+}
+
+/// Journals directory, replaced by the status message if any
+pub fn journals_dir_paragraph(state: &State) -> Paragraph<'_> {
+  let s = state.styles();
+  if let Some(status) = &state.status {
+    return Paragraph::new(Line::styled(status.as_str(), s.accent));
+  }
+  let dir = state.journals_dir.display();
+  if state.global_undo {
+    Paragraph::new(format!("global undo | {dir}"))
+  } else {
+    Paragraph::new(dir.to_string())
+  }
+}
+
+pub fn date_paragraph<'a>(
+  date: NaiveDate,
+  period: Period,
+) -> Paragraph<'a> {
+  let date = match period {
+    Period::Day => date.format("%a, %-d %b %Y"),
+    Period::Week => date.format("Wk %V of %G"),
+    Period::Month => date.format("%B %Y"),
+  };
+  Paragraph::new(format!("< {date} >"))
 }
 
 pub fn record_list(state: &State) -> List<'_> {
-  let items: Vec<_> = state
+  let s = state.styles();
+  if state.period != Period::Day {
+    return totals_list(state);
+  }
+  let mut items: Vec<_> = state
     .list
     .iter()
     .enumerate()
-    .map(|(i, dt)| {
-      let text = format!("{}) {}", i + 1, dt.format("%R"));
+    .map(|(i, rec)| {
+      let time = rec.time.format("%R");
+      let text = format!("{}) {time} {}", i + 1, rec.details());
       ListItem::new(text).style(if i == state.list.selected() {
-        styles::ACCENT.reversed()
+        s.accent.reversed()
+      } else {
+        s.primary
+      })
+    })
+    .collect();
+  if state.malformed > 0 {
+    let text = format!("! {} bad, run fsck", state.malformed);
+    items.push(ListItem::new(text).style(s.red));
+  }
+  List::new(items)
+}
+
+/// Records count per day of the period
+fn totals_list(state: &State) -> List<'_> {
+  let s = state.styles();
+  let today = Local::now().date_naive();
+  let items: Vec<_> = state
+    .totals
+    .iter()
+    .filter(|(date, _)| *date <= today)
+    .map(|&(date, count)| {
+      let text = format!("{}  {count}", date.format("%a %e %b"));
+      ListItem::new(text).style(if count == 0 {
+        s.grey
       } else {
-        styles::PRIMARY
+        s.primary
       })
     })
     .collect();
   List::new(items)
 }
 
+pub fn history_list(state: &State, scroll: usize) -> List<'_> {
+  let s = state.styles();
+  if state.history.is_empty() {
+    return List::new([ListItem::new("no actions yet")])
+      .style(s.grey);
+  }
+  let items: Vec<_> = state
+    .history
+    .iter()
+    .skip(scroll)
+    .map(|(entry, undone)| {
+      let at = entry.at.format("%-d %b %R");
+      let description = entry.action.description();
+      if *undone {
+        ListItem::new(format!("{at}  {description} (undone)"))
+          .style(s.grey)
+      } else {
+        ListItem::new(format!("{at}  {description}"))
+      }
+    })
+    .collect();
+  List::new(items)
+}
+
 pub fn level_bar(state: &State) -> BarChart<'_> {
+  let s = state.styles();
   let percentage = state.level.percentage();
   let level = (percentage * 100.).round() as _;
   let count = state.level.count();
@@ -60,10 +157,10 @@ pub fn level_bar(state: &State) -> BarChart<'_> {
         .value(level)
         .text_value(format!("{count}/{target} ({level}%)"))]),
     )
-    .bar_style(styles::RED.fg(level_color(&state.level)))
+    .bar_style(s.red.fg(level_color(&s, &state.level)))
 }
 
-fn level_color(level: &Level) -> Color {
+fn level_color(s: &Styles, level: &Level) -> Color {
   const RGB_MAX: f32 = u8::MAX as f32;
   const K: f32 = 2.5;
   let percentage = level.percentage();
@@ -73,7 +170,7 @@ fn level_color(level: &Level) -> Color {
   if level.is_positive() {
     (r, g, b) = (g, r, b);
   }
-  Color::Rgb(r as _, g as _, b as _)
+  s.fit(Color::Rgb(r as _, g as _, b as _))
 }
 
 pub struct DaysBarChart<'a> {
@@ -82,7 +179,8 @@ pub struct DaysBarChart<'a> {
 }
 
 impl<'a> DaysBarChart<'a> {
-  const GAP: u16 = 2;
+  pub const BAR_WIDTH: u16 = 2;
+  pub const GAP: u16 = 2;
 
   pub fn new(state: &'a State) -> Self {
     Self { state, block: None }
@@ -102,25 +200,31 @@ impl<'a> DaysBarChart<'a> {
   }
 
   fn bar_chart(&self) -> BarChart<'a> {
+    let s = self.state.styles();
     let bars: Vec<_> = self
       .state
       .recs_by_date
       .iter()
       .map(|&(date, count)| {
-        let label = date.format("%e").to_string();
-        if date == self.state.date {
+        let label = match self.state.period {
+          Period::Day => date.format("%e"),
+          Period::Week => date.format("%V"),
+          Period::Month => date.format("%m"),
+        }
+        .to_string();
+        if date == self.selected() {
           Bar::default()
-            .label(Line::styled(label, styles::ACCENT))
+            .label(Line::styled(label, s.accent))
             .value(count as _)
-            .style(styles::ACCENT)
+            .style(s.accent)
         } else {
           Bar::default()
-            .label(Line::styled(label, styles::PRIMARY))
+            .label(Line::styled(label, s.primary))
             .value(count as _)
-            .style(
-              styles::PRIMARY
-                .fg(level_color(&self.state.level.for_count(count))),
-            )
+            .style(s.primary.fg(level_color(
+              &s,
+              &self.state.level.for_count(count),
+            )))
         }
       })
       .collect();
@@ -134,13 +238,20 @@ impl<'a> DaysBarChart<'a> {
       .map_or(0, |v| *v);
 
     BarChart::default()
-      .bar_width(2)
+      .bar_width(Self::BAR_WIDTH)
       .bar_gap(Self::GAP)
       .max(bar_max(max_val as _))
       .data(BarGroup::default().bars(&bars))
   }
 
+  /// First day of the bar of the date
+  fn selected(&self) -> NaiveDate {
+    self.state.period.start(self.state.date)
+  }
+
+  /// Weekdays under days, months under weeks and years under months
   fn weekdays_paragraph(&self) -> Paragraph<'a> {
+    let s = self.state.styles();
     let space = " ".repeat(Self::GAP as _);
     let weekdays: Vec<_> = self
       .state
@@ -148,13 +259,21 @@ impl<'a> DaysBarChart<'a> {
       .iter()
       .map(|(date, _)| {
         let weekday = weekday(*date);
-        let weekday_symbols = &weekday.to_string()[..2];
-        let style = if date == &self.state.date {
-          styles::ACCENT
-        } else if weekday == Weekday::Sun {
-          styles::RED
+        let weekday_symbols = match self.state.period {
+          Period::Day => weekday.to_string()[..2].to_owned(),
+          Period::Week => {
+            date.format("%b").to_string()[..2].to_owned()
+          }
+          Period::Month => date.format("%y").to_string(),
+        };
+        let style = if *date == self.selected() {
+          s.accent
+        } else if self.state.period == Period::Day
+          && weekday == Weekday::Sun
+        {
+          s.red
         } else {
-          styles::PRIMARY
+          s.primary
         };
         Span::styled(format!("{weekday_symbols}{space}"), style)
       })
@@ -174,6 +293,136 @@ impl<'a> Widget for DaysBarChart<'a> {
   }
 }
 
+/// Weeks by weekdays grid of records count per day up to today,
+/// as many recent weeks as fit
+pub struct Heatmap<'a> {
+  state: &'a State,
+  cursor: NaiveDate,
+  block: Option<Block<'a>>,
+}
+
+impl<'a> Heatmap<'a> {
+  const CELL_WIDTH: u16 = 2;
+  const LABELS_WIDTH: u16 = 3;
+
+  pub fn new(state: &'a State, cursor: NaiveDate) -> Self {
+    Self {
+      state,
+      cursor,
+      block: None,
+    }
+  }
+
+  pub fn block(mut self, block: Block<'a>) -> Self {
+    self.block = Some(block);
+    self
+  }
+
+  fn months_line(&self, start: NaiveDate, weeks: u64) -> Line<'a> {
+    let s = self.state.styles();
+    let mut line = " ".repeat(Self::LABELS_WIDTH.into());
+    for week in 0..weeks {
+      let monday = start + Days::new(week * 7);
+      let new_month = week == 0
+        || (monday - Days::new(7)).month() != monday.month();
+      if new_month && line.chars().count() <= line_width(week) {
+        let pad = line_width(week) - line.chars().count();
+        line += &" ".repeat(pad);
+        line += &monday.format("%b").to_string();
+      }
+    }
+    Line::styled(line, s.primary)
+  }
+
+  fn weekday_line(
+    &self,
+    start: NaiveDate,
+    weeks: u64,
+    weekday: u64,
+    counts: &HashMap<NaiveDate, usize>,
+  ) -> Line<'a> {
+    let s = self.state.styles();
+    let today = Local::now().date_naive();
+    let label = match weekday {
+      0 => "Mo ",
+      2 => "We ",
+      4 => "Fr ",
+      _ => "   ",
+    };
+    let mut spans = vec![Span::styled(label, s.primary)];
+    for week in 0..weeks {
+      let date = start + Days::new(week * 7 + weekday);
+      if date > today {
+        break;
+      }
+      let count = counts.get(&date).copied().unwrap_or_default();
+      let style = if count == 0 {
+        s.grey
+      } else {
+        s.primary
+          .fg(level_color(&s, &self.state.level.for_count(count)))
+      };
+      let style = if date == self.cursor {
+        style.reversed()
+      } else {
+        style
+      };
+      spans.push(Span::styled("■", style));
+      spans.push(Span::raw(" "));
+    }
+    Line::from(spans)
+  }
+
+  fn status_line(
+    &self,
+    counts: &HashMap<NaiveDate, usize>,
+  ) -> Line<'a> {
+    let s = self.state.styles();
+    let count = counts.get(&self.cursor).copied().unwrap_or_default();
+    Line::styled(
+      format!(
+        "{}: {count} records, ENTER - open, ESC - back",
+        self.cursor.format("%a, %-d %b %Y")
+      ),
+      s.grey,
+    )
+  }
+}
+
+/// Column of the week in the heatmap
+fn line_width(week: u64) -> usize {
+  (u64::from(Heatmap::LABELS_WIDTH)
+    + week * u64::from(Heatmap::CELL_WIDTH)) as usize
+}
+
+impl<'a> Widget for Heatmap<'a> {
+  fn render(mut self, mut area: Rect, buf: &mut Buffer) {
+    if let Some(block) = self.block.take() {
+      let inner = block.inner(area);
+      block.render(area, buf);
+      area = inner;
+    }
+    let today = Local::now().date_naive();
+    let fit = area.width.saturating_sub(Self::LABELS_WIDTH)
+      / Self::CELL_WIDTH;
+    let weeks = HEATMAP_WEEKS.min(fit.into());
+    let start = today.week(Weekday::Mon).first_day()
+      - Days::new(weeks.saturating_sub(1) * 7);
+    // Scrolls back to the cursor when not all weeks fit
+    let start = start.min(self.cursor.week(Weekday::Mon).first_day());
+    let counts: HashMap<_, _> =
+      self.state.recs_by_day.iter().copied().collect();
+
+    let mut lines = vec![self.months_line(start, weeks)];
+    for weekday in 0..7 {
+      lines.push(self.weekday_line(start, weeks, weekday, &counts));
+    }
+    lines.push(Line::default());
+    lines.push(self.status_line(&counts));
+    Paragraph::new(lines).render(area, buf);
+  }
+}
+
 fn weekday(date: NaiveDate) -> Weekday {
   date.and_time(NaiveTime::default()).weekday()
 }
@@ -184,6 +433,7 @@ fn bar_max(max_val: u64) -> u64 {
 }
 
 pub fn time_smoke_records_bar_chart(state: &State) -> BarChart<'_> {
+  let s = state.styles();
   const HOURS_COUNT: u8 = 24;
 
   let bars: Vec<_> = (0..HOURS_COUNT)
@@ -198,11 +448,11 @@ pub fn time_smoke_records_bar_chart(state: &State) -> BarChart<'_> {
           if state
             .list
             .selected_item()
-            .is_some_and(|dt| h == dt.time().hour() as _)
+            .is_some_and(|rec| h == rec.time.hour() as _)
           {
-            styles::ACCENT
+            s.accent
           } else {
-            styles::RED
+            s.red
           },
         )
     })
@@ -244,6 +494,98 @@ fn iter_months(start: Month) -> impl Iterator<Item = Month> {
   })
 }
 
-pub fn help_paragraph<'a>() -> Paragraph<'a> {
-  Paragraph::new("SPACE - add record, u - undo, U - redo, ESC - quit")
+/// Help entries packed into lines of `area`, as many as fit
+pub fn help_paragraph<'a>(
+  state: &State,
+  area: Rect,
+) -> Paragraph<'a> {
+  const SEPARATOR: &str = "  ";
+  let width = usize::from(area.width);
+  let height = usize::from(area.height);
+  let mut lines: Vec<String> = vec![];
+  for entry in &state.help {
+    let len = entry.chars().count();
+    let fits = |line: &String| {
+      line.chars().count() + SEPARATOR.len() + len <= width
+    };
+    if let Some(line) = lines.last_mut().filter(|line| fits(line)) {
+      line.push_str(SEPARATOR);
+      line.push_str(entry);
+    } else if lines.len() < height && len <= width {
+      lines.push(entry.clone());
+    } else {
+      break;
+    }
+  }
+  Paragraph::new(
+    lines.into_iter().map(Line::from).collect::<Vec<_>>(),
+  )
+}
+
+pub fn too_small_paragraph<'a>(
+  width: u16,
+  height: u16,
+) -> Paragraph<'a> {
+  Paragraph::new(vec![
+    Line::from("Terminal is too small"),
+    Line::from(format!(
+      "{width}x{height}, at least {}x{} needed",
+      layout::MIN_WIDTH,
+      layout::MIN_HEIGHT,
+    )),
+  ])
+}
+
+pub fn prompt_paragraph<'a>(
+  state: &'a State,
+  prompt: &'a Prompt,
+) -> Paragraph<'a> {
+  let s = state.styles();
+  let status = match &prompt.error {
+    Some(err) => Line::styled(err.as_str(), s.red),
+    None => Line::styled(
+      format!(
+        "{}, ENTER - {}, ESC - cancel",
+        prompt.kind.hint(),
+        prompt.kind.submit()
+      ),
+      s.grey,
+    ),
+  };
+  let matches = if prompt.kind == PromptKind::SwitchTab {
+    tab_matches_line(state, &prompt.input)
+  } else {
+    Line::default()
+  };
+  Paragraph::new(vec![
+    Line::styled(prompt.input.as_str(), s.primary),
+    matches,
+    status,
+  ])
+}
+
+/// Matching tab titles, the one to switch to highlighted
+fn tab_matches_line<'a>(state: &'a State, query: &str) -> Line<'a> {
+  let s = state.styles();
+  let spans = state
+    .matching_tabs(query.trim())
+    .into_iter()
+    .enumerate()
+    .map(|(i, (_, tab))| {
+      let style = if i == 0 { s.accent } else { s.grey };
+      Span::styled(format!("{}  ", tab.text), style)
+    });
+  Line::from(spans.collect::<Vec<_>>())
+}
+
+pub fn error_paragraph(message: &str) -> Paragraph<'_> {
+  Paragraph::new(message)
+}
+
+pub fn error_help_paragraph<'a>() -> Paragraph<'a> {
+  Paragraph::new("fix the problem and restart, ESC - quit")
+}
+
+pub fn error_banner_help_paragraph<'a>() -> Paragraph<'a> {
+  Paragraph::new("r - retry, ESC - dismiss")
 }